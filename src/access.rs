@@ -0,0 +1,128 @@
+/*
+ * HTTP request records, such as those dropshot logs when it completes a
+ * request, displayed as the one-line-per-request access logs familiar from
+ * other web servers: either in our own compact form, or in the Apache
+ * combined log format for use with existing tools that analyse access logs.
+ */
+
+use chrono::prelude::*;
+
+use crate::{renderers::latency, BunyanEntry, Colour};
+
+pub struct Request {
+    time: DateTime<Utc>,
+    remote: Option<String>,
+    method: String,
+    uri: String,
+    status: String,
+    bytes: Option<u64>,
+    latency: Option<String>,
+    version: Option<String>,
+    referer: Option<String>,
+    user_agent: Option<String>,
+}
+
+fn string(v: &serde_json::Value) -> String {
+    match v {
+        serde_json::Value::String(s) => s.to_string(),
+        other => other.to_string(),
+    }
+}
+
+impl Request {
+    /*
+     * Only the record of a completed request has the status, so that is
+     * the one we use for each request.
+     */
+    pub fn new(be: &BunyanEntry) -> Option<Request> {
+        let x = &be.extra;
+        Some(Request {
+            time: be.time,
+            remote: x.get("remote_addr").map(string),
+            method: x.get("method")?.as_str()?.to_string(),
+            uri: x.get("uri")?.as_str()?.to_string(),
+            status: string(x.get("response_code")?),
+            /*
+             * Dropshot does not log the size of the response, but other
+             * servers may.
+             */
+            bytes: ["response_bytes", "bytes", "content_length"]
+                .iter()
+                .find_map(|k| x.get(*k).and_then(|b| b.as_u64())),
+            latency: x.get("latency_us").and_then(latency),
+            version: x.get("http_version").map(string),
+            referer: x.get("referer").map(string),
+            user_agent: ["user_agent", "user-agent"]
+                .iter()
+                .find_map(|k| x.get(*k).map(string)),
+        })
+    }
+
+    /*
+     * The status is coloured by its class, so that failures stand out.
+     */
+    fn status(&self, colour: Colour) -> String {
+        let code = match (colour, self.status.chars().next()) {
+            (Colour::None, _) => None,
+            (_, Some('5')) => Some(31),
+            (_, Some('4')) => Some(33),
+            _ => None,
+        };
+        match code {
+            Some(c) => format!("\x1b[{}m{}\x1b[0m", c, self.status),
+            None => self.status.clone(),
+        }
+    }
+
+    pub fn access(&self, colour: Colour) -> String {
+        format!(
+            "{} {} {} {} {} {}",
+            self.remote.as_deref().unwrap_or("-"),
+            self.method,
+            self.uri,
+            self.status(colour),
+            self.bytes.map(|b| b.to_string()).as_deref().unwrap_or("-"),
+            self.latency.as_deref().unwrap_or("-"),
+        )
+    }
+
+    /*
+     * The Apache combined log format:
+     *
+     *  host ident user [time] "request" status bytes "referer" "user agent"
+     *
+     * The host is just the address, without the port.  We know nothing of
+     * the ident or user, and leave them blank, as servers conventionally do.
+     */
+    pub fn clf(&self) -> String {
+        let host = self.remote.as_deref().map(|r| match r.rsplit_once(':') {
+            /*
+             * An IPv6 address with a port is in brackets.
+             */
+            Some((a, p)) if p.chars().all(|c| c.is_ascii_digit()) => {
+                a.trim_start_matches('[').trim_end_matches(']')
+            }
+            _ => r,
+        });
+        let quoted = |s: Option<&str>| match s {
+            Some(s) => format!("\"{}\"", s.replace('"', "\\\"")),
+            None => "\"-\"".to_string(),
+        };
+        format!(
+            "{} - - [{}] \"{} {} {}\" {} {} {} {}",
+            host.unwrap_or("-"),
+            self.time.format("%d/%b/%Y:%H:%M:%S %z"),
+            self.method,
+            self.uri,
+            match self.version.as_deref() {
+                Some(v) if v.starts_with("HTTP/") => v.to_string(),
+                Some(v) => format!("HTTP/{}", v),
+                None => "HTTP/1.1".to_string(),
+            },
+            self.status,
+            self.bytes.map(|b| b.to_string()).as_deref().unwrap_or("-"),
+            quoted(self.referer.as_deref()),
+            quoted(self.user_agent.as_deref()),
+        )
+    }
+}
@@ -0,0 +1,155 @@
+/*
+ * Recognise Rust backtraces (as printed by std::backtrace::Backtrace, or
+ * anyhow) within messages and string fields, and lay them out as a block
+ * with the frames aligned.  Frames from the standard library and the async
+ * runtime are rarely of interest, so they can be folded away.
+ */
+
+use anyhow::{bail, Result};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    Full,
+    Short,
+    Hide,
+}
+
+impl std::str::FromStr for Mode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Mode> {
+        Ok(match s {
+            "full" => Mode::Full,
+            "short" => Mode::Short,
+            "hide" => Mode::Hide,
+            other => bail!("unknown backtrace mode {:?}", other),
+        })
+    }
+}
+
+/*
+ * Frames whose function begins with one of these are hidden in short mode.
+ */
+const BORING: &[&str] = &[
+    "std::",
+    "core::",
+    "alloc::",
+    "backtrace::",
+    "tokio::",
+    "futures",
+    "<std::",
+    "<core::",
+    "<alloc::",
+    "<tokio::",
+    "<futures",
+    "rust_begin_unwind",
+    "__rust",
+    "__libc_start",
+    "_start",
+    "start_thread",
+];
+
+struct Frame<'a> {
+    number: &'a str,
+    function: &'a str,
+    locations: Vec<&'a str>,
+}
+
+impl Frame<'_> {
+    fn boring(&self) -> bool {
+        BORING.iter().any(|b| self.function.starts_with(b))
+    }
+}
+
+/*
+ * A frame line is of the form "  12: some::function".
+ */
+fn frame(l: &str) -> Option<(&str, &str)> {
+    let (n, f) = l.trim_start().split_once(": ")?;
+    if n.is_empty() || !n.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some((n, f.trim()))
+}
+
+/*
+ * A location line, following a frame line, is of the form
+ * "      at src/main.rs:12:5".
+ */
+fn location(l: &str) -> Option<&str> {
+    l.trim_start().strip_prefix("at ")
+}
+
+/*
+ * If the string contains a backtrace, return it with the backtrace laid out
+ * as requested; otherwise, return None so that the caller can render the
+ * string as usual.  Lines before and after the backtrace are left as they
+ * are.
+ */
+pub fn render(s: &str, mode: Mode) -> Option<String> {
+    let lines = s.lines().collect::<Vec<_>>();
+    let start = lines.iter().position(|l| frame(l).is_some())?;
+
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut end = start;
+    while end < lines.len() {
+        if let Some((number, function)) = frame(lines[end]) {
+            frames.push(Frame { number, function, locations: Vec::new() });
+        } else if let (Some(loc), Some(f)) =
+            (location(lines[end]), frames.last_mut())
+        {
+            f.locations.push(loc);
+        } else {
+            break;
+        }
+        end += 1;
+    }
+
+    /*
+     * A lone numbered line could be anything; e.g., a list in a message.
+     * Require either a few frames or at least one source location.
+     */
+    if frames.len() < 3 && frames.iter().all(|f| f.locations.is_empty()) {
+        return None;
+    }
+
+    let width = frames.iter().map(|f| f.number.len()).max().unwrap_or(1);
+    let mut out: Vec<String> =
+        lines[..start].iter().map(|l| l.to_string()).collect();
+
+    match mode {
+        Mode::Hide => {
+            out.push(format!("  <backtrace of {} frames hidden>", frames.len()))
+        }
+        Mode::Full | Mode::Short => {
+            let mut hidden = 0;
+            let fold = |hidden: &mut usize, out: &mut Vec<String>| {
+                if *hidden > 0 {
+                    out.push(format!(
+                        "  {:>width$}  ... {} frame{} hidden ...",
+                        "",
+                        hidden,
+                        if *hidden == 1 { "" } else { "s" }
+                    ));
+                    *hidden = 0;
+                }
+            };
+
+            for f in frames.iter() {
+                if mode == Mode::Short && f.boring() {
+                    hidden += 1;
+                    continue;
+                }
+                fold(&mut hidden, &mut out);
+                out.push(format!("  {:>width$}: {}", f.number, f.function));
+                for l in f.locations.iter() {
+                    out.push(format!("  {:>width$}    at {}", "", l));
+                }
+            }
+            fold(&mut hidden, &mut out);
+        }
+    }
+
+    out.extend(lines[end..].iter().map(|l| l.to_string()));
+    Some(out.join("\n"))
+}
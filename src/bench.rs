@@ -0,0 +1,190 @@
+/*
+ * "looker bench": measure how quickly we can process an input, breaking the
+ * time down into the parse, filter, and format stages.  The input is read
+ * into memory first so that the results are not dominated by I/O.
+ */
+
+use std::{
+    io::{BufRead, BufReader, Read},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::{
+    emit_bare, emit_record, parse_filter, parse_line, BunyanLevel, Colour,
+    Columns, Format, Line, Style,
+};
+
+struct Stage {
+    name: &'static str,
+    lines: usize,
+    bytes: usize,
+    time: Duration,
+}
+
+impl Stage {
+    fn print(&self) {
+        let secs = self.time.as_secs_f64().max(f64::MIN_POSITIVE);
+        println!(
+            "{:<8} {:>10} {:>10.3}s {:>12.0} {:>10.1}",
+            self.name,
+            self.lines,
+            self.time.as_secs_f64(),
+            self.lines as f64 / secs,
+            self.bytes as f64 / secs / 1_000_000.0,
+        );
+    }
+}
+
+pub fn run(args: &[String]) -> Result<()> {
+    let mut opts = getopts::Options::new();
+    opts.optflag("", "help", "usage information");
+    opts.optopt("f", "", "read input from a file rather than stdin", "FILE");
+    opts.optopt(
+        "l",
+        "level",
+        "only format messages at or above this level",
+        "LEVEL",
+    );
+    opts.optopt(
+        "o",
+        "output",
+        "output format to measure: \"short\" (default), \"long\", or \"bare\"",
+        "FORMAT",
+    );
+    opts.optopt("c", "", "filter the input with a rhai script", "SCRIPT");
+
+    let usage = "looker bench [OPTIONS] [LOOKUPS...]";
+    let a = match opts.parse(args) {
+        Ok(a) => {
+            if a.opt_present("help") {
+                println!("{}", opts.usage(usage));
+                return Ok(());
+            }
+            a
+        }
+        Err(e) => {
+            eprintln!("{}\nERROR: {}", usage, e);
+            std::process::exit(1);
+        }
+    };
+    crate::config::init(None)?;
+
+    let mut lookups = a.free.clone();
+    let format = match a.opt_str("o").as_deref() {
+        Some("short") | None => Format::Short,
+        Some("long") => Format::Long,
+        Some("bare") => {
+            if lookups.is_empty() {
+                lookups.push("msg".into());
+            }
+            Format::Bare
+        }
+        Some(other) => bail!("unknown format type {:?}", other),
+    };
+    let level =
+        a.opt_str("l").as_deref().map(BunyanLevel::from_str).transpose()?;
+    let lookups = &lookups;
+    let mut filter = a.opt_str("c").map(parse_filter).transpose()?;
+    let columns = Columns::new(if matches!(format, Format::Bare) {
+        lookups
+    } else {
+        &[]
+    })?;
+
+    let input: Box<dyn Read> = if let Some(p) = a.opt_str("f") {
+        Box::new(
+            std::fs::File::open(&p)
+                .map_err(|e| anyhow!("opening file {p:?}: {e}"))?,
+        )
+    } else {
+        Box::new(std::io::stdin())
+    };
+    let lines = BufReader::new(input).lines().collect::<Result<Vec<_>, _>>()?;
+    let total = lines.iter().map(|l| l.len() + 1).sum::<usize>();
+    println!("input: {} lines, {:.1} MB", lines.len(), total as f64 / 1e6);
+    println!();
+
+    let start = Instant::now();
+    let mut records = Vec::new();
+    for l in lines.iter() {
+        if let Line::Record(j, be) = parse_line(l) {
+            records.push((l.len() + 1, j, be));
+        }
+    }
+    let parse = Stage {
+        name: "parse",
+        lines: lines.len(),
+        bytes: total,
+        time: start.elapsed(),
+    };
+    drop(lines);
+
+    let lines = records.len();
+    let bytes = records.iter().map(|r| r.0).sum();
+    let start = Instant::now();
+    let mut matched = Vec::new();
+    for r in records.iter_mut() {
+        if level.as_ref().map(|level| &r.2.level < level).unwrap_or(false) {
+            continue;
+        }
+        if let Some(filter) = &mut filter {
+            if !filter.matches(&mut r.1)? {
+                continue;
+            }
+        }
+        matched.push(r);
+    }
+    let filter = Stage { name: "filter", lines, bytes, time: start.elapsed() };
+
+    /*
+     * Render into a sink, so that we measure the cost of formatting rather
+     * than that of the terminal.
+     */
+    let mut out = std::io::sink();
+    let style = Style {
+        colour: Colour::None,
+        fmt: format,
+        lookups,
+        unescape: false,
+        unicode: false,
+        backtrace: crate::backtrace::Mode::Full,
+        hexdump: false,
+        decode_b64: &[],
+        error_chain: false,
+        wrap: None,
+        highlight: &[],
+        exclude: &[],
+        marks: &[],
+        span_tree: false,
+        embedded_json: false,
+        zone: crate::Zone::Utc,
+        time: true,
+    };
+    let start = Instant::now();
+    for (_, j, be) in matched.iter() {
+        if matches!(format, Format::Bare) {
+            emit_bare(&mut out, j.clone(), &columns)?;
+        } else {
+            emit_record(&mut out, be, &style, None, None, None)?;
+        }
+    }
+    let format = Stage {
+        name: "format",
+        lines: matched.len(),
+        bytes: matched.iter().map(|r| r.0).sum(),
+        time: start.elapsed(),
+    };
+
+    println!(
+        "{:<8} {:>10} {:>11} {:>12} {:>10}",
+        "STAGE", "RECORDS", "TIME", "LINES/S", "MB/S"
+    );
+    parse.print();
+    filter.print();
+    format.print();
+
+    Ok(())
+}
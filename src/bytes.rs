@@ -0,0 +1,112 @@
+/*
+ * Binary data in log records, which generally appears either as an array of
+ * byte values or as a base64 string.
+ */
+
+use serde_json::Value;
+
+fn b64_digit(c: u8) -> Option<u32> {
+    Some(match c {
+        b'A'..=b'Z' => c - b'A',
+        b'a'..=b'z' => c - b'a' + 26,
+        b'0'..=b'9' => c - b'0' + 52,
+        b'+' | b'-' => 62,
+        b'/' | b'_' => 63,
+        _ => return None,
+    } as u32)
+}
+
+/*
+ * Decode base64 in either the standard or the URL-safe alphabet, with or
+ * without padding.
+ */
+pub fn decode_b64(s: &str) -> Option<Vec<u8>> {
+    let s = s.trim().trim_end_matches('=');
+    if s.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    for chunk in s.as_bytes().chunks(4) {
+        let mut n = 0;
+        for (i, c) in chunk.iter().enumerate() {
+            n |= b64_digit(*c)? << (18 - 6 * i);
+        }
+        let bytes = n.to_be_bytes();
+        out.extend_from_slice(&bytes[1..chunk.len()]);
+    }
+    Some(out)
+}
+
+/*
+ * The smallest amount of data that we will consider to be binary, as short
+ * arrays of numbers are often just that.
+ */
+const MIN_BYTES: usize = 8;
+
+/*
+ * Determine whether a field value looks like binary data, returning the bytes
+ * if so.  Arrays of small integers are taken to be bytes.  All manner of
+ * ordinary words and identifiers are also valid base64, so strings must be
+ * padded, must have the mix of characters that encoded data tends to have,
+ * and must decode to something other than printable text.
+ */
+pub fn binary(v: &Value) -> Option<Vec<u8>> {
+    let b: Vec<u8> = match v {
+        Value::Array(a) => a
+            .iter()
+            .map(|b| b.as_u64().filter(|b| *b <= 255).map(|b| b as u8))
+            .collect::<Option<_>>()?,
+        Value::String(s) => {
+            if s.len() % 4 != 0
+                || !s.chars().any(|c| c.is_ascii_uppercase())
+                || !s.chars().any(|c| c.is_ascii_lowercase())
+                || !s.chars().any(|c| c.is_ascii_digit() || "+/=".contains(c))
+            {
+                return None;
+            }
+            let b = decode_b64(s)?;
+            if b.iter().all(|c| c.is_ascii_graphic() || c.is_ascii_whitespace())
+            {
+                return None;
+            }
+            b
+        }
+        _ => return None,
+    };
+
+    (b.len() >= MIN_BYTES).then_some(b)
+}
+
+/*
+ * Produce a dump in the style of "hexdump -C": the offset, sixteen bytes in
+ * hex, and the same bytes as ASCII.
+ */
+pub fn hexdump(b: &[u8]) -> Vec<String> {
+    b.chunks(16)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut hex = String::new();
+            for j in 0..16 {
+                if j == 8 {
+                    hex.push(' ');
+                }
+                match chunk.get(j) {
+                    Some(c) => hex += &format!("{:02x} ", c),
+                    None => hex += "   ",
+                }
+            }
+            let ascii = chunk
+                .iter()
+                .map(|c| {
+                    if c.is_ascii_graphic() || *c == b' ' {
+                        *c as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>();
+            format!("{:08x}  {} |{}|", i * 16, hex, ascii)
+        })
+        .collect()
+}
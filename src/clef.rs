@@ -0,0 +1,107 @@
+/*
+ * Records in the Compact Log Event Format of Serilog (and Seq), in which the
+ * standard properties begin with "@": the time is "@t", the level is "@l"
+ * (information, if it is absent), and the message is either "@m" or the
+ * message template "@mt", whose holes we fill from the properties of the
+ * record.  An exception ("@x") becomes the "exception" field, and the
+ * source context (the logger, in effect) the name.
+ */
+
+use serde_json::{json, Map, Value};
+
+fn level(l: &str) -> Option<u8> {
+    Some(match l {
+        "Verbose" => 10,
+        "Debug" => 20,
+        "Information" => 30,
+        "Warning" => 40,
+        "Error" => 50,
+        "Fatal" => 60,
+        _ => return None,
+    })
+}
+
+/*
+ * Fill the holes of a message template, such as "{User}" or "{Elapsed:0.0}",
+ * with the values of the properties; "{{" and "}}" stand for the braces
+ * themselves.  A hole whose property is missing is left as it is.
+ */
+fn render(template: &str, props: &Map<String, Value>) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        out.push_str(&rest[..i]);
+        let (brace, after) = rest[i..].split_at(1);
+        if after.starts_with(brace) {
+            out.push_str(brace);
+            rest = &after[1..];
+            continue;
+        }
+        let hole = match brace {
+            "{" => after.find('}').map(|j| &after[..j]),
+            _ => None,
+        };
+        let Some(hole) = hole else {
+            out.push_str(brace);
+            rest = after;
+            continue;
+        };
+        rest = &after[hole.len() + 1..];
+
+        let name = hole
+            .trim_start_matches(['@', '$'])
+            .split([',', ':'])
+            .next()
+            .unwrap_or("");
+        match props.get(name) {
+            Some(Value::String(s)) => out.push_str(s),
+            Some(v) => out.push_str(&v.to_string()),
+            None => {
+                out.push('{');
+                out.push_str(hole);
+                out.push('}');
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+pub fn to_bunyan(j: &Value) -> Option<Value> {
+    let o = j.as_object()?;
+    let time = o.get("@t")?.as_str()?;
+    let level = match o.get("@l") {
+        Some(l) => level(l.as_str()?)?,
+        None => 30,
+    };
+    let msg = match (o.get("@m"), o.get("@mt")) {
+        (Some(m), _) => m.as_str()?.to_string(),
+        (None, Some(mt)) => render(mt.as_str()?, o),
+        (None, None) => String::new(),
+    };
+    let name = o.get("SourceContext").and_then(Value::as_str).unwrap_or("-");
+
+    let mut r = json!({
+        "v": 0,
+        "level": level,
+        "name": name,
+        "hostname": "",
+        "pid": 0,
+        "time": time,
+        "msg": msg,
+    });
+    let r_o = r.as_object_mut().unwrap();
+    if let Some(x) = o.get("@x") {
+        r_o.insert("exception".into(), x.clone());
+    }
+    let extra = o.iter().filter(|(k, _)| {
+        !matches!(
+            k.as_str(),
+            "@t" | "@l" | "@m" | "@mt" | "@x" | "@r" | "SourceContext"
+        )
+    });
+    for (k, v) in extra {
+        r_o.entry(k.as_str()).or_insert_with(|| v.clone());
+    }
+    Some(r)
+}
@@ -0,0 +1,82 @@
+/*
+ * Group records by message template, in order to get a sense of what an
+ * unfamiliar service logs: each cluster is reported with the number of
+ * records in it and a few examples of the messages it covers.
+ */
+
+use std::collections::BTreeMap;
+
+use crate::{bold, dim, BunyanEntry, Colour};
+
+/*
+ * The number of distinct example messages kept for each cluster.
+ */
+const EXAMPLES: usize = 2;
+
+#[derive(Default)]
+struct Cluster {
+    count: u64,
+    examples: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct Clusters {
+    total: u64,
+    clusters: BTreeMap<(String, String), Cluster>,
+}
+
+impl Clusters {
+    pub fn new() -> Clusters {
+        Default::default()
+    }
+
+    pub fn record(&mut self, be: &BunyanEntry) {
+        let comp = be.component.as_deref().unwrap_or(&be.name).to_string();
+        let first = be.msg.lines().next().unwrap_or("");
+        let tmpl = crate::template::template(first);
+
+        let c = self.clusters.entry((comp, tmpl)).or_default();
+        c.count += 1;
+        if c.examples.len() < EXAMPLES && !c.examples.iter().any(|e| e == first)
+        {
+            c.examples.push(first.to_string());
+        }
+        self.total += 1;
+    }
+
+    pub fn report(&self, colour: Colour) {
+        let mut rows = self.clusters.iter().collect::<Vec<_>>();
+        rows.sort_by_key(|(_, c)| std::cmp::Reverse(c.count));
+
+        println!(
+            "{}",
+            bold(
+                &format!("{:>8} {:>6}  COMPONENT: TEMPLATE", "COUNT", "%"),
+                colour
+            )
+        );
+        for ((comp, tmpl), c) in rows {
+            println!(
+                "{:>8} {:>6.2}  {}: {}",
+                c.count,
+                100.0 * c.count as f64 / self.total as f64,
+                comp,
+                tmpl
+            );
+            /*
+             * There is no point in an example that is the same as the
+             * template; i.e., where nothing was abstracted.
+             */
+            for e in c.examples.iter().filter(|e| *e != tmpl) {
+                println!("{:17}  {}", "", dim(&format!("e.g. {}", e), colour));
+            }
+        }
+
+        println!();
+        println!(
+            "{} clusters from {} records",
+            self.clusters.len(),
+            self.total
+        );
+    }
+}
@@ -0,0 +1,167 @@
+/*
+ * Compare the volume of log traffic in two time windows of the same input;
+ * e.g., before and after an upgrade.  Records are counted per component and
+ * per message template, and the largest changes are reported first.
+ */
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Result};
+use chrono::prelude::*;
+
+use crate::{bold, parse_time, BunyanEntry, Colour};
+
+struct Window {
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    total: u64,
+    first: Option<DateTime<Utc>>,
+    last: Option<DateTime<Utc>>,
+}
+
+impl Window {
+    fn contains(&self, t: &DateTime<Utc>) -> bool {
+        self.start.map(|s| *t >= s).unwrap_or(true)
+            && self.end.map(|e| *t < e).unwrap_or(true)
+    }
+
+    fn describe(&self) -> String {
+        let fmt = |t: Option<DateTime<Utc>>| {
+            t.map(|t| t.to_rfc3339_opts(SecondsFormat::Millis, true))
+                .unwrap_or_else(|| "...".to_string())
+        };
+        let span = match (self.first, self.last) {
+            (Some(f), Some(l)) => {
+                format!(
+                    " over {}",
+                    crate::elapsed(l - f).trim_start_matches('+')
+                )
+            }
+            _ => "".to_string(),
+        };
+        format!(
+            "{} to {}: {} records{}",
+            fmt(self.start),
+            fmt(self.end),
+            self.total,
+            span
+        )
+    }
+}
+
+/*
+ * Parse a window of the form "START..END", where either end may be omitted to
+ * leave that side of the window open.
+ */
+fn parse_window(s: &str) -> Result<Window> {
+    let Some((start, end)) = s.split_once("..") else {
+        bail!("time window {:?} should be of the form START..END", s);
+    };
+    let parse = |t: &str| {
+        if t.trim().is_empty() {
+            Ok(None)
+        } else {
+            parse_time(t).map(Some)
+        }
+    };
+
+    Ok(Window {
+        start: parse(start)?,
+        end: parse(end)?,
+        total: 0,
+        first: None,
+        last: None,
+    })
+}
+
+pub struct Compare {
+    windows: [Window; 2],
+    components: BTreeMap<String, [u64; 2]>,
+    messages: BTreeMap<(String, String), [u64; 2]>,
+}
+
+impl Compare {
+    pub fn new(windows: &[String]) -> Result<Compare> {
+        let [a, b] = windows else {
+            bail!("--compare must be specified exactly twice");
+        };
+
+        Ok(Compare {
+            windows: [parse_window(a)?, parse_window(b)?],
+            components: Default::default(),
+            messages: Default::default(),
+        })
+    }
+
+    pub fn record(&mut self, be: &BunyanEntry) {
+        let comp = be.component.as_deref().unwrap_or(&be.name).to_string();
+        let tmpl =
+            crate::template::template(be.msg.lines().next().unwrap_or(""));
+
+        for (i, w) in self.windows.iter_mut().enumerate() {
+            if !w.contains(&be.time) {
+                continue;
+            }
+
+            w.total += 1;
+            w.first = Some(w.first.map_or(be.time, |f| f.min(be.time)));
+            w.last = Some(w.last.map_or(be.time, |l| l.max(be.time)));
+            self.components.entry(comp.clone()).or_default()[i] += 1;
+            let counts =
+                self.messages.entry((comp.clone(), tmpl.clone())).or_default();
+            counts[i] += 1;
+        }
+    }
+
+    pub fn report(&self, colour: Colour) {
+        println!("{} {}", bold("A:", colour), self.windows[0].describe());
+        println!("{} {}", bold("B:", colour), self.windows[1].describe());
+
+        /*
+         * Order rows by the magnitude of the change, so that whatever got
+         * noisier (or quieter) is at the top.
+         */
+        fn sorted<K>(m: &BTreeMap<K, [u64; 2]>) -> Vec<(&K, [u64; 2])> {
+            let mut rows = m.iter().map(|(k, c)| (k, *c)).collect::<Vec<_>>();
+            rows.sort_by_key(|(_, [a, b])| std::cmp::Reverse(a.abs_diff(*b)));
+            rows
+        }
+
+        println!();
+        println!(
+            "{}",
+            bold(
+                &format!("{:>8} {:>8} {:>9}  COMPONENT", "A", "B", "DELTA"),
+                colour
+            )
+        );
+        for (c, [a, b]) in sorted(&self.components) {
+            println!("{:>8} {:>8} {:>+9}  {}", a, b, b as i64 - a as i64, c);
+        }
+
+        println!();
+        println!(
+            "{}",
+            bold(
+                &format!(
+                    "{:>8} {:>8} {:>9}  COMPONENT: MESSAGE",
+                    "A", "B", "DELTA"
+                ),
+                colour
+            )
+        );
+        for ((c, m), [a, b]) in sorted(&self.messages) {
+            if a == b {
+                continue;
+            }
+            println!(
+                "{:>8} {:>8} {:>+9}  {}: {}",
+                a,
+                b,
+                b as i64 - a as i64,
+                c,
+                m
+            );
+        }
+    }
+}
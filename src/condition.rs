@@ -0,0 +1,116 @@
+/*
+ * Simple field comparisons from the command line; e.g.,
+ * "--where latency_us>500000".  These cover the common numeric thresholds
+ * without the need to write a rhai script.
+ */
+
+use std::cmp::Ordering;
+
+use anyhow::{bail, Result};
+use serde_json::Value;
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+/*
+ * Operators are listed such that the two character forms are tried before
+ * their one character prefixes.
+ */
+const OPS: &[(&str, Op)] = &[
+    (">=", Op::Ge),
+    ("<=", Op::Le),
+    ("!=", Op::Ne),
+    ("==", Op::Eq),
+    (">", Op::Gt),
+    ("<", Op::Lt),
+    ("=", Op::Eq),
+];
+
+#[derive(Debug)]
+pub struct Condition {
+    field: String,
+    op: Op,
+    value: String,
+    number: Option<f64>,
+}
+
+impl Condition {
+    pub fn parse(s: &str) -> Result<Condition> {
+        let Some((pos, sym, op)) = s.char_indices().find_map(|(i, _)| {
+            OPS.iter()
+                .find(|(sym, _)| s[i..].starts_with(sym))
+                .map(|(sym, op)| (i, *sym, *op))
+        }) else {
+            bail!(
+                "condition {:?} should be of the form FIELD OP VALUE, \
+                where OP is one of: >, >=, <, <=, =, !=",
+                s
+            );
+        };
+
+        let field = s[..pos].trim().to_string();
+        let value = s[pos + sym.len()..].trim().to_string();
+        if field.is_empty() || value.is_empty() {
+            bail!("condition {:?} is missing a field name or value", s);
+        }
+        let number = value.parse::<f64>().ok();
+        if number.is_none() && !matches!(op, Op::Eq | Op::Ne) {
+            bail!("condition {:?} requires a numeric value", s);
+        }
+
+        Ok(Condition { field, op, value, number })
+    }
+
+    /*
+     * Records that do not have the field are never matched, in keeping with
+     * the treatment of missing fields in rhai filters.
+     */
+    pub fn matches(&self, j: &Value) -> bool {
+        let Some(v) = j.get(&self.field) else {
+            return false;
+        };
+
+        /*
+         * Numbers are often logged as strings (e.g., dropshot's
+         * "response_code"), so accept either representation.
+         */
+        let num = match v {
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) => s.trim().parse::<f64>().ok(),
+            _ => None,
+        };
+
+        let ord = match (num, self.number) {
+            (Some(a), Some(b)) => a.partial_cmp(&b),
+            _ => {
+                let s = match v {
+                    Value::String(s) => s.to_string(),
+                    other => other.to_string(),
+                };
+                match self.op {
+                    Op::Eq | Op::Ne => Some(s.as_str().cmp(&self.value)),
+                    _ => None,
+                }
+            }
+        };
+        let Some(ord) = ord else {
+            return false;
+        };
+
+        match self.op {
+            Op::Lt => ord == Ordering::Less,
+            Op::Le => ord != Ordering::Greater,
+            Op::Gt => ord == Ordering::Greater,
+            Op::Ge => ord != Ordering::Less,
+            Op::Eq => ord == Ordering::Equal,
+            Op::Ne => ord != Ordering::Equal,
+        }
+    }
+}
@@ -0,0 +1,124 @@
+/*
+ * The optional configuration file, in JSON format.  It is read from the path
+ * given with --config, or in $LOOKER_CONFIG, or else from
+ * $XDG_CONFIG_HOME/looker/config.json (or ~/.config/looker/config.json) if
+ * that file exists.
+ */
+
+use std::{path::PathBuf, sync::OnceLock};
+
+use anyhow::{anyhow, bail, Result};
+use serde::Deserialize;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /*
+     * Additional levels, or changes to the display of the standard levels.
+     */
+    #[serde(default)]
+    pub levels: Vec<LevelConfig>,
+    /*
+     * Templates used to condense records from particular components into a
+     * single line when -R is used.
+     */
+    #[serde(default)]
+    pub renderers: Vec<crate::renderers::TemplateRenderer>,
+    /*
+     * Rules for displaying records that match some condition in a particular
+     * style.
+     */
+    #[serde(default)]
+    pub highlight: Vec<crate::highlight::RuleConfig>,
+    /*
+     * The time zone in which to show timestamps, if not given with --tz.
+     */
+    pub tz: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct LevelConfig {
+    pub value: u8,
+    /*
+     * Names that may be used to refer to this level; e.g., with -l.
+     */
+    #[serde(default)]
+    pub names: Vec<String>,
+    /*
+     * The abbreviation used in place of the level name in output, such as
+     * "NOTE" for a "notice" level.
+     */
+    pub abbrev: Option<String>,
+    /*
+     * ANSI colour numbers to use for the level in 16 and 256 colour mode
+     * respectively; e.g., 93 for bright yellow, or 214 for orange.
+     */
+    #[serde(alias = "color16")]
+    pub colour16: Option<u8>,
+    #[serde(alias = "color256")]
+    pub colour256: Option<u8>,
+}
+
+impl Config {
+    pub fn level(&self, value: u8) -> Option<&LevelConfig> {
+        self.levels.iter().find(|l| l.value == value)
+    }
+
+    pub fn level_by_name(&self, name: &str) -> Option<&LevelConfig> {
+        self.levels
+            .iter()
+            .find(|l| l.names.iter().any(|n| n.eq_ignore_ascii_case(name)))
+    }
+}
+
+fn default_path() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(d) if !d.is_empty() => PathBuf::from(d),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("looker").join("config.json"))
+}
+
+/*
+ * Load the configuration file.  An explicitly nominated file must exist,
+ * but the file in the default location is optional.
+ */
+pub fn init(path: Option<&str>) -> Result<()> {
+    let path = match path
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("LOOKER_CONFIG").map(PathBuf::from))
+    {
+        Some(p) => Some(p),
+        None => default_path().filter(|p| p.exists()),
+    };
+
+    let config = if let Some(p) = path {
+        let f = std::fs::read_to_string(&p)
+            .map_err(|e| anyhow!("reading config file {p:?}: {e}"))?;
+        let c: Config = serde_json::from_str(&f)
+            .map_err(|e| anyhow!("parsing config file {p:?}: {e}"))?;
+        if let Some(l) =
+            c.levels.iter().find(|l| l.abbrev.as_deref() == Some(""))
+        {
+            bail!("config file {p:?}: level {} has an empty abbrev", l.value);
+        }
+        crate::diag::emit(
+            crate::diag::Level::Info,
+            "config",
+            &format!("loaded configuration from {p:?}"),
+            &[],
+        );
+        c
+    } else {
+        Config::default()
+    };
+
+    CONFIG.set(config).map_err(|_| anyhow!("configuration loaded twice"))
+}
+
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
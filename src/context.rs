@@ -0,0 +1,83 @@
+/*
+ * Records around each match, in the manner of grep's -A and -B.  Records
+ * that do not match are rendered as they are read; we hold on to the last
+ * few in case a match follows, and show those that follow a match until we
+ * have shown enough.  As with grep, "--" separates groups of records that
+ * are not adjacent in the input.
+ */
+
+use std::{collections::VecDeque, io::Write};
+
+use anyhow::Result;
+
+use crate::{dim, Colour};
+
+pub struct Context {
+    before: usize,
+    after: usize,
+    held: VecDeque<Vec<u8>>,
+    /*
+     * The number of records after the last match still to be shown.
+     */
+    owed: usize,
+    /*
+     * Whether we have shown anything yet, and whether we have since left
+     * anything out.
+     */
+    shown: bool,
+    gap: bool,
+}
+
+impl Context {
+    pub fn new(before: usize, after: usize) -> Context {
+        Context {
+            before,
+            after,
+            held: VecDeque::new(),
+            owed: 0,
+            shown: false,
+            gap: false,
+        }
+    }
+
+    /*
+     * A record (or other line) that did not match, already rendered.
+     */
+    pub fn skipped(
+        &mut self,
+        out: &mut dyn Write,
+        text: Vec<u8>,
+    ) -> Result<()> {
+        if self.owed > 0 {
+            self.owed -= 1;
+            out.write_all(&text)?;
+            return Ok(());
+        }
+        self.held.push_back(text);
+        if self.held.len() > self.before {
+            self.held.pop_front();
+            self.gap = true;
+        }
+        Ok(())
+    }
+
+    /*
+     * A match is about to be shown, so the records before it are too.
+     */
+    pub fn matched(
+        &mut self,
+        out: &mut dyn Write,
+        colour: Colour,
+    ) -> Result<()> {
+        if self.shown && self.gap {
+            writeln!(out, "{}", dim("--", colour))?;
+        }
+        for text in self.held.drain(..) {
+            out.write_all(&text)?;
+        }
+        self.shown = true;
+        self.gap = false;
+        self.owed = self.after;
+        Ok(())
+    }
+}
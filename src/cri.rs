@@ -0,0 +1,46 @@
+/*
+ * The log files of CRI runtimes (containerd and CRI-O) prefix each line that
+ * a container wrote with the time, the stream, and a tag, as in:
+ *
+ *     2024-03-01T10:00:00.123456789Z stdout F {"v":0,...}
+ *
+ * and kubectl logs --timestamps prefixes each with the time alone.  We strip
+ * the prefix and read the line within.  Should that be a JSON object without
+ * a time of its own, it is given the time of the prefix.
+ */
+
+use chrono::prelude::*;
+
+/*
+ * The time of the prefix, and the line after it.
+ */
+pub fn strip(l: &str) -> Option<(&str, &str)> {
+    let (time, rest) = l.split_once(' ')?;
+    DateTime::parse_from_rfc3339(time).ok()?;
+
+    let rest = match rest.split_once(' ') {
+        Some(("stdout" | "stderr", tagged)) => match tagged.split_once(' ') {
+            Some((tag, line))
+                if tag.chars().all(|c| c.is_ascii_uppercase()) =>
+            {
+                line
+            }
+            _ => tagged,
+        },
+        _ => rest,
+    };
+    Some((time, rest))
+}
+
+/*
+ * The line, given the time of the prefix if it is a JSON object without one.
+ */
+pub fn stamp(l: &str, time: &str) -> Option<String> {
+    let mut j = serde_json::from_str::<serde_json::Value>(l).ok()?;
+    let o = j.as_object_mut()?;
+    if o.contains_key("time") {
+        return None;
+    }
+    o.insert("time".into(), time.into());
+    Some(j.to_string())
+}
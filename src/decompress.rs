@@ -0,0 +1,44 @@
+/*
+ * Compressed input, such as a rotated log, is recognised by the magic
+ * number at the start of the data rather than by the name of the file, and
+ * decompressed as it is read by the usual tool for the format.
+ */
+
+use std::{
+    io::{Cursor, Read},
+    process::Command,
+};
+
+use anyhow::Result;
+
+use crate::remote::Spawned;
+
+const FORMATS: &[(&[u8], &str)] = &[
+    (&[0x1f, 0x8b], "gzip"),
+    (&[0x28, 0xb5, 0x2f, 0xfd], "zstd"),
+    (&[0xfd, b'7', b'z', b'X', b'Z', 0x00], "xz"),
+    (b"BZh", "bzip2"),
+];
+
+/*
+ * Enough to hold the longest magic number.
+ */
+const MAGIC_BYTES: usize = 6;
+
+pub fn open(mut r: Box<dyn Read + Send>) -> Result<Box<dyn Read>> {
+    let mut head = Vec::with_capacity(MAGIC_BYTES);
+    (&mut r).take(MAGIC_BYTES as u64).read_to_end(&mut head)?;
+
+    /*
+     * Whatever we find, the bytes we looked at are still part of the input.
+     */
+    let r = Box::new(Cursor::new(head.clone()).chain(r));
+    Ok(match FORMATS.iter().find(|(magic, _)| head.starts_with(magic)) {
+        Some((_, prog)) => {
+            let mut c = Command::new(prog);
+            c.arg("-dc");
+            Box::new(Spawned::new(c, Some(r))?)
+        }
+        None => r,
+    })
+}
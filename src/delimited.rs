@@ -0,0 +1,60 @@
+/*
+ * Selected properties of each record as CSV or TSV, with a header row, for
+ * loading into a spreadsheet or a database such as sqlite.
+ *
+ * CSV fields are quoted as described in RFC 4180.  TSV has no means of
+ * quoting, so tabs, newlines, and backslashes within a value are written as
+ * escape sequences instead, as PostgreSQL and others expect.
+ */
+
+use serde_json::Value;
+
+use crate::Format;
+
+fn text(v: Option<&Value>) -> String {
+    match v {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.to_string(),
+        Some(other) => other.to_string(),
+    }
+}
+
+fn csv(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn tsv(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out += "\\\\",
+            '\t' => out += "\\t",
+            '\n' => out += "\\n",
+            '\r' => out += "\\r",
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn join(fmt: Format, fields: impl Iterator<Item = String>) -> String {
+    match fmt {
+        Format::Tsv => fields.map(|f| tsv(&f)).collect::<Vec<_>>().join("\t"),
+        _ => fields.map(|f| csv(&f)).collect::<Vec<_>>().join(","),
+    }
+}
+
+pub fn header(fmt: Format, lookups: &[String]) -> String {
+    join(fmt, lookups.iter().cloned())
+}
+
+pub fn row<'a>(
+    fmt: Format,
+    values: impl Iterator<Item = Option<&'a Value>>,
+) -> String {
+    join(fmt, values.map(text))
+}
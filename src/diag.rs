@@ -0,0 +1,110 @@
+/*
+ * Messages about looker itself, rather than the log being read: warnings
+ * about the input, lines that could not be parsed, and so on.  These go to
+ * stderr, either as plain text for a person to read, or as one JSON object
+ * per line for a program that runs looker to consume.  Each message has a
+ * level, and a kind that identifies the condition without having to parse
+ * the text.
+ */
+
+use std::{
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, AtomicU8, Ordering},
+        Mutex,
+    },
+};
+
+use anyhow::{bail, Result};
+use chrono::prelude::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error = 0,
+    Warn = 1,
+    Info = 2,
+    Debug = 3,
+}
+
+impl Level {
+    fn name(&self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+        }
+    }
+}
+
+/*
+ * By default, errors and warnings are shown.  Each -v shows one more level.
+ */
+static VERBOSITY: AtomicU8 = AtomicU8::new(Level::Warn as u8);
+static JSON: AtomicBool = AtomicBool::new(false);
+/*
+ * The watchdog thread also produces diagnostics, so make sure that messages
+ * are not interleaved.
+ */
+static LOCK: Mutex<()> = Mutex::new(());
+
+pub fn init(verbose: usize, format: Option<&str>) -> Result<()> {
+    let json = match format {
+        None | Some("text") => false,
+        Some("json") => true,
+        Some(other) => bail!("unknown diagnostics format {:?}", other),
+    };
+    JSON.store(json, Ordering::Relaxed);
+    let v = (Level::Warn as usize + verbose).min(Level::Debug as usize);
+    VERBOSITY.store(v as u8, Ordering::Relaxed);
+    Ok(())
+}
+
+pub fn enabled(level: Level) -> bool {
+    level as u8 <= VERBOSITY.load(Ordering::Relaxed)
+}
+
+/*
+ * Emit a message, with optional additional fields that are included in the
+ * JSON form; the text form is just the message, which should say the same
+ * thing.
+ */
+pub fn emit(
+    level: Level,
+    kind: &str,
+    msg: &str,
+    fields: &[(&str, serde_json::Value)],
+) {
+    if !enabled(level) {
+        return;
+    }
+
+    let line = if JSON.load(Ordering::Relaxed) {
+        let mut o = serde_json::Map::new();
+        o.insert(
+            "time".into(),
+            Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true).into(),
+        );
+        o.insert("level".into(), level.name().into());
+        o.insert("kind".into(), kind.into());
+        o.insert("msg".into(), msg.into());
+        for (k, v) in fields {
+            o.insert(k.to_string(), v.clone());
+        }
+        serde_json::Value::Object(o).to_string()
+    } else {
+        format!("looker: {}: {}", level.name(), msg)
+    };
+
+    let _g = LOCK.lock().unwrap();
+    let mut e = std::io::stderr().lock();
+    writeln!(e, "{}", line).ok();
+}
+
+pub fn warn(kind: &str, msg: &str) {
+    emit(Level::Warn, kind, msg, &[]);
+}
+
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
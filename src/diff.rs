@@ -0,0 +1,345 @@
+/*
+ * "looker diff A B": align two logs by message template and show the records
+ * that appear in only one of them.  This is most useful for comparing a
+ * failing run of some program against a passing one, where timestamps and
+ * identifiers differ but the overall sequence of events should not.
+ */
+
+use std::{
+    io::{BufRead, BufReader},
+    str::FromStr,
+};
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::{
+    bold, choose_colour, level, parse_line, BunyanEntry, BunyanLevel, Colour,
+    Line,
+};
+
+/*
+ * The alignment time grows with the product of the length of the logs and
+ * the number of differences between them, so refuse to align logs that have
+ * essentially nothing in common.  Memory use does not depend on this limit.
+ */
+const MAX_EDITS: usize = 10_000;
+
+enum Op {
+    Same(usize),
+    OnlyA(usize),
+    OnlyB(usize),
+}
+
+fn read_records(
+    path: &str,
+    level: Option<&BunyanLevel>,
+) -> Result<Vec<BunyanEntry>> {
+    let f = std::fs::File::open(path)
+        .map_err(|e| anyhow!("opening file {path:?}: {e}"))?;
+
+    let mut out = Vec::new();
+    for l in BufReader::new(f).lines() {
+        let l = l.map_err(|e| anyhow!("reading file {path:?}: {e}"))?;
+        let Line::Record(_, be) = parse_line(&l) else {
+            continue;
+        };
+        if level.map(|level| &be.level < level).unwrap_or(false) {
+            continue;
+        }
+        out.push(be);
+    }
+    Ok(out)
+}
+
+/*
+ * Records are considered the same if they come from the same place and have
+ * the same message template.
+ */
+fn key(be: &BunyanEntry) -> String {
+    format!(
+        "{}\0{}\0{}\0{}",
+        be.name,
+        be.component.as_deref().unwrap_or(""),
+        be.level.render(),
+        crate::template::template(&be.msg),
+    )
+}
+
+/*
+ * Compute a shortest edit script between the two key sequences using the
+ * linear space refinement of the Myers algorithm: find a point on an optimal
+ * path by searching from both ends at once, then solve the two halves on
+ * either side of it.  Only the current frontier is kept for each search, so
+ * memory is proportional to the length of the logs rather than to the square
+ * of the number of differences.
+ */
+fn align(a: &[String], b: &[String]) -> Result<Vec<Op>> {
+    enum Work {
+        Diff(usize, usize, usize, usize),
+        Same(usize, usize),
+    }
+
+    let mut ops = Vec::new();
+    let mut work = vec![Work::Diff(0, a.len(), 0, b.len())];
+    while let Some(w) = work.pop() {
+        let (mut a0, mut a1, mut b0, mut b1) = match w {
+            Work::Diff(a0, a1, b0, b1) => (a0, a1, b0, b1),
+            Work::Same(a0, a1) => {
+                ops.extend((a0..a1).map(Op::Same));
+                continue;
+            }
+        };
+
+        /*
+         * Strip the common prefix and suffix first, as for similar logs this
+         * is where most of the records are.
+         */
+        while a0 < a1 && b0 < b1 && a[a0] == b[b0] {
+            ops.push(Op::Same(a0));
+            a0 += 1;
+            b0 += 1;
+        }
+        let suf = a[a0..a1]
+            .iter()
+            .rev()
+            .zip(b[b0..b1].iter().rev())
+            .take_while(|(x, y)| x == y)
+            .count();
+        work.push(Work::Same(a1 - suf, a1));
+        a1 -= suf;
+        b1 -= suf;
+
+        if a0 == a1 || b0 == b1 {
+            ops.extend((a0..a1).map(Op::OnlyA));
+            ops.extend((b0..b1).map(Op::OnlyB));
+            continue;
+        }
+
+        match bisect(&a[a0..a1], &b[b0..b1])? {
+            Some((x, y)) => {
+                work.push(Work::Diff(a0 + x, a1, b0 + y, b1));
+                work.push(Work::Diff(a0, a0 + x, b0, b0 + y));
+            }
+            None => {
+                ops.extend((a0..a1).map(Op::OnlyA));
+                ops.extend((b0..b1).map(Op::OnlyB));
+            }
+        }
+    }
+    Ok(ops)
+}
+
+/*
+ * Find the middle snake of an optimal path through the edit graph of two
+ * non-empty sequences that differ in their first and last elements, and
+ * return the point at which to split them.  Returns None if the sequences
+ * have nothing in common.
+ */
+fn bisect(a: &[String], b: &[String]) -> Result<Option<(usize, usize)>> {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let max = (n + m + 1) / 2;
+    let off = max;
+    let len = 2 * max as usize + 2;
+
+    /*
+     * vf[k] and vb[k] are the furthest reaching x on diagonal k from the top
+     * left and from the bottom right respectively, or -1 if not yet reached.
+     */
+    let mut vf = vec![-1isize; len];
+    let mut vb = vec![-1isize; len];
+    vf[off as usize + 1] = 0;
+    vb[off as usize + 1] = 0;
+
+    let delta = n - m;
+    let front = delta % 2 != 0;
+
+    /*
+     * Diagonals that have run off the edge of the graph need not be searched
+     * again, so trim them from either end of the range.
+     */
+    let (mut kf_start, mut kf_end, mut kb_start, mut kb_end) = (0, 0, 0, 0);
+    for d in 0..max {
+        /*
+         * Each step extends the search by one edit from both ends.
+         */
+        if 2 * d as usize > MAX_EDITS {
+            bail!(
+                "logs are too different to align (more than {} differences)",
+                MAX_EDITS,
+            );
+        }
+
+        let mut k = -d + kf_start;
+        while k <= d - kf_end {
+            let i = (off + k) as usize;
+            let mut x = if k == -d || (k != d && vf[i - 1] < vf[i + 1]) {
+                vf[i + 1]
+            } else {
+                vf[i - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            vf[i] = x;
+            if x > n {
+                kf_end += 2;
+            } else if y > m {
+                kf_start += 2;
+            } else if front {
+                let j = off + delta - k;
+                if j >= 0
+                    && (j as usize) < len
+                    && vb[j as usize] != -1
+                    && x >= n - vb[j as usize]
+                {
+                    return Ok(Some((x as usize, y as usize)));
+                }
+            }
+            k += 2;
+        }
+
+        let mut k = -d + kb_start;
+        while k <= d - kb_end {
+            let i = (off + k) as usize;
+            let mut x = if k == -d || (k != d && vb[i - 1] < vb[i + 1]) {
+                vb[i + 1]
+            } else {
+                vb[i - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n
+                && y < m
+                && a[(n - x - 1) as usize] == b[(m - y - 1) as usize]
+            {
+                x += 1;
+                y += 1;
+            }
+            vb[i] = x;
+            if x > n {
+                kb_end += 2;
+            } else if y > m {
+                kb_start += 2;
+            } else if !front {
+                let j = off + delta - k;
+                if j >= 0 && (j as usize) < len && vf[j as usize] != -1 {
+                    let fx = vf[j as usize];
+                    let fy = fx - (j - off);
+                    if fx >= n - x {
+                        return Ok(Some((fx as usize, fy as usize)));
+                    }
+                }
+            }
+            k += 2;
+        }
+    }
+
+    Ok(None)
+}
+
+fn line(mark: &str, be: &BunyanEntry, colour: Colour) {
+    let mut n = bold(&be.name, colour);
+    if let Some(c) = &be.component {
+        if c != &be.name {
+            n += &format!(" ({})", c);
+        }
+    }
+    let msg = be.msg.lines().next().unwrap_or("");
+    let mark = match (colour, mark) {
+        (Colour::None, _) | (_, " ") => mark.to_string(),
+        (_, "-") => "\x1b[31m-\x1b[0m".to_string(),
+        _ => "\x1b[32m+\x1b[0m".to_string(),
+    };
+    println!(
+        "{} {} {} {}: {}",
+        mark,
+        be.time.format("%H:%M:%S%.3fZ"),
+        level(&be.level, colour),
+        n,
+        msg,
+    );
+}
+
+pub fn run(args: &[String]) -> Result<()> {
+    let mut opts = getopts::Options::new();
+    opts.optflag("", "help", "usage information");
+    opts.optflag("C", "", "force coloured output when not a tty");
+    opts.optflag("N", "", "no terminal formatting");
+    opts.optflag("a", "all", "show records common to both logs as well");
+    opts.optopt(
+        "l",
+        "level",
+        "only compare messages at or above this level",
+        "LEVEL",
+    );
+
+    let usage = "looker diff [OPTIONS] FILE_A FILE_B";
+    let a = match opts.parse(args) {
+        Ok(a) => {
+            if a.opt_present("help") {
+                println!("{}", opts.usage(usage));
+                return Ok(());
+            }
+            a
+        }
+        Err(e) => {
+            eprintln!("{}\nERROR: {}", usage, e);
+            std::process::exit(1);
+        }
+    };
+    crate::config::init(None)?;
+    if a.free.len() != 2 {
+        eprintln!("{}\nERROR: two files are required", usage);
+        std::process::exit(1);
+    }
+
+    let colour = choose_colour(a.opt_present("N"), a.opt_present("C"));
+    let level =
+        a.opt_str("l").as_deref().map(BunyanLevel::from_str).transpose()?;
+
+    let ra = read_records(&a.free[0], level.as_ref())?;
+    let rb = read_records(&a.free[1], level.as_ref())?;
+    let ka = ra.iter().map(key).collect::<Vec<_>>();
+    let kb = rb.iter().map(key).collect::<Vec<_>>();
+
+    let (mut only_a, mut only_b, mut same) = (0, 0, 0);
+    let mut gap = false;
+    for op in align(&ka, &kb)? {
+        match op {
+            Op::Same(i) => {
+                same += 1;
+                if a.opt_present("a") {
+                    line(" ", &ra[i], colour);
+                } else {
+                    gap = true;
+                }
+            }
+            Op::OnlyA(i) | Op::OnlyB(i) => {
+                /*
+                 * Separate runs of differences so that it is clear which
+                 * records were adjacent in the original logs.
+                 */
+                if gap && only_a + only_b > 0 {
+                    println!("{}", crate::dim("...", colour));
+                }
+                gap = false;
+                if let Op::OnlyA(_) = op {
+                    only_a += 1;
+                    line("-", &ra[i], colour);
+                } else {
+                    only_b += 1;
+                    line("+", &rb[i], colour);
+                }
+            }
+        }
+    }
+
+    println!(
+        "{} records only in {}, {} only in {}, {} in common",
+        only_a, a.free[0], only_b, a.free[1], same,
+    );
+
+    Ok(())
+}
@@ -0,0 +1,39 @@
+/*
+ * Docker's json-file logging driver wraps each line that a container writes
+ * in an envelope, as in:
+ *
+ *     {"log":"...\n","stream":"stdout","time":"2024-03-01T10:00:00.1Z"}
+ *
+ * The line within is generally a record in its own right, which we read in
+ * place of the envelope.  If it is not, the envelope is shown as a record
+ * with the line as its message, and the stream as a field.
+ */
+
+use serde_json::{json, Value};
+
+/*
+ * The line within an envelope.
+ */
+pub fn payload(j: &Value) -> Option<&str> {
+    let o = j.as_object()?;
+    if o.len() != 3 || !o.get("stream")?.is_string() {
+        return None;
+    }
+    o.get("time")?.as_str()?;
+    let log = o.get("log")?.as_str()?;
+    Some(log.strip_suffix('\n').unwrap_or(log))
+}
+
+pub fn to_bunyan(j: &Value) -> Option<Value> {
+    let msg = payload(j)?;
+    Some(json!({
+        "v": 0,
+        "level": 30,
+        "name": "-",
+        "hostname": "",
+        "pid": 0,
+        "time": j.get("time")?,
+        "msg": msg.strip_suffix('\r').unwrap_or(msg),
+        "stream": j.get("stream")?,
+    }))
+}
@@ -0,0 +1,83 @@
+/*
+ * Records in the Elastic Common Schema, as written by the ECS logging
+ * libraries and shipped by filebeat or vector.  The schema nests its fields
+ * ("log.level" is the "level" of the "log" object), but loggers are free to
+ * write either "log": { "level": ... } or "log.level", and commonly mix the
+ * two, so we flatten the nested objects to dotted names throughout, and
+ * take the time from "@timestamp", the level from "log.level", and the name
+ * from "service.name" (or "log.logger").
+ */
+
+use serde_json::{json, Map, Value};
+
+fn level(l: &str) -> Option<u8> {
+    Some(match l.to_ascii_lowercase().as_str() {
+        "trace" => 10,
+        "debug" => 20,
+        "info" | "notice" => 30,
+        "warn" | "warning" => 40,
+        "error" => 50,
+        "fatal" | "critical" | "alert" | "emergency" => 60,
+        _ => return None,
+    })
+}
+
+fn flatten(prefix: &str, o: &Map<String, Value>, out: &mut Map<String, Value>) {
+    for (k, v) in o {
+        let k = if prefix.is_empty() {
+            k.to_string()
+        } else {
+            format!("{}.{}", prefix, k)
+        };
+        match v {
+            Value::Object(o) if !o.is_empty() => flatten(&k, o, out),
+            _ => {
+                out.insert(k, v.clone());
+            }
+        }
+    }
+}
+
+pub fn to_bunyan(j: &Value) -> Option<Value> {
+    let o = j.as_object()?;
+    if !o.get("@timestamp")?.is_string() {
+        return None;
+    }
+    let mut fields = Map::new();
+    flatten("", o, &mut fields);
+    if !fields.contains_key("log.level") && !fields.contains_key("ecs.version")
+    {
+        return None;
+    }
+
+    let time = fields.remove("@timestamp")?;
+    let level = match fields.remove("log.level") {
+        Some(l) => level(l.as_str()?)?,
+        None => 30,
+    };
+    let msg = match fields.remove("message") {
+        Some(Value::String(s)) => s,
+        Some(other) => other.to_string(),
+        None => String::new(),
+    };
+    let get = |k: &str| fields.get(k).and_then(Value::as_str);
+    let name = get("service.name").or_else(|| get("log.logger")).unwrap_or("-");
+    let hostname =
+        get("host.hostname").or_else(|| get("host.name")).unwrap_or("");
+    let pid = fields.get("process.pid").and_then(Value::as_u64).unwrap_or(0);
+
+    let mut r = json!({
+        "v": 0,
+        "level": level,
+        "name": name,
+        "hostname": hostname,
+        "pid": pid,
+        "time": time,
+        "msg": msg,
+    });
+    let r_o = r.as_object_mut().unwrap();
+    for (k, v) in fields {
+        r_o.entry(k).or_insert(v);
+    }
+    Some(r)
+}
@@ -0,0 +1,36 @@
+/*
+ * Messages often carry a serialised JSON value, as in "request body:
+ * {...}", which is hard to read on one line.  We look for the first object
+ * (or array of objects or arrays) within a message that parses as JSON, so
+ * that it can be shown pretty-printed beneath the rest of the message, and
+ * be examined by filters.  An array of scalars is more likely to be
+ * something like the "[ 1.234]" of a kernel message, so it is left alone.
+ */
+
+use serde_json::Value;
+
+fn worthwhile(v: &Value) -> bool {
+    match v {
+        Value::Object(o) => !o.is_empty(),
+        Value::Array(a) => {
+            !a.is_empty()
+                && a.iter()
+                    .all(|v| matches!(v, Value::Object(_) | Value::Array(_)))
+        }
+        _ => false,
+    }
+}
+
+/*
+ * The text before the embedded value, the value itself, and the text after
+ * it.
+ */
+pub fn find(msg: &str) -> Option<(&str, Value, &str)> {
+    msg.match_indices(['{', '[']).find_map(|(i, _)| {
+        let mut values =
+            serde_json::Deserializer::from_str(&msg[i..]).into_iter::<Value>();
+        let v = values.next()?.ok().filter(worthwhile)?;
+        let end = i + values.byte_offset();
+        Some((&msg[..i], v, &msg[end..]))
+    })
+}
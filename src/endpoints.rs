@@ -0,0 +1,123 @@
+/*
+ * A summary of the requests handled by a dropshot server, per endpoint: how
+ * many there were, how many failed, and the distribution of their latency.
+ * Endpoints are identified by the method and the pattern of the URI, so that
+ * requests for different instances of the same resource are grouped.
+ */
+
+use std::collections::HashMap;
+
+use crate::{
+    bold, renderers::latency, template::uri_pattern, BunyanEntry, Colour,
+};
+
+#[derive(Default)]
+struct Endpoint {
+    /*
+     * Latency of each request in microseconds.
+     */
+    latencies: Vec<f64>,
+    client_errors: u64,
+    server_errors: u64,
+}
+
+#[derive(Default)]
+pub struct Endpoints {
+    endpoints: HashMap<(String, String), Endpoint>,
+}
+
+/*
+ * The status code is sometimes logged as a number, and sometimes as a
+ * string.
+ */
+fn status(v: &serde_json::Value) -> Option<u64> {
+    v.as_u64().or_else(|| v.as_str().and_then(|s| s.parse().ok()))
+}
+
+/*
+ * The value at the given percentile of a sorted list, by the nearest-rank
+ * method.
+ */
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+impl Endpoints {
+    pub fn new() -> Endpoints {
+        Default::default()
+    }
+
+    /*
+     * Only records of completed requests have both the status and the
+     * latency, and those are the ones we count.
+     */
+    pub fn record(&mut self, be: &BunyanEntry) {
+        let (Some(method), Some(uri), Some(us)) = (
+            be.extra.get("method").and_then(|m| m.as_str()),
+            be.extra.get("uri").and_then(|u| u.as_str()),
+            be.extra.get("latency_us").and_then(|l| l.as_f64()),
+        ) else {
+            return;
+        };
+
+        let e = self
+            .endpoints
+            .entry((method.to_string(), uri_pattern(uri)))
+            .or_default();
+        e.latencies.push(us);
+        match be.extra.get("response_code").and_then(status) {
+            Some(400..=499) => e.client_errors += 1,
+            Some(500..=599) => e.server_errors += 1,
+            _ => (),
+        }
+    }
+
+    pub fn report(&mut self, colour: Colour) {
+        if self.endpoints.is_empty() {
+            println!("no completed requests");
+            return;
+        }
+
+        let mut rows = self.endpoints.iter_mut().collect::<Vec<_>>();
+        rows.sort_by(|a, b| {
+            b.1.latencies.len().cmp(&a.1.latencies.len()).then(a.0.cmp(b.0))
+        });
+
+        println!(
+            "{}",
+            bold(
+                &format!(
+                    "{:>8} {:>6} {:>6} {:>10} {:>10} {:>10} {:>10}  ENDPOINT",
+                    "COUNT", "4XX%", "5XX%", "P50", "P90", "P99", "MAX"
+                ),
+                colour
+            )
+        );
+        let mut total = 0;
+        for ((method, pattern), e) in rows {
+            e.latencies.sort_by(f64::total_cmp);
+            let n = e.latencies.len();
+            total += n;
+            let pct = |c: u64| 100.0 * c as f64 / n as f64;
+            let l = |p: f64| {
+                latency(&percentile(&e.latencies, p).into()).unwrap_or_default()
+            };
+            println!(
+                "{:>8} {:>6.2} {:>6.2} {:>10} {:>10} {:>10} {:>10}  {} {}",
+                n,
+                pct(e.client_errors),
+                pct(e.server_errors),
+                l(50.0),
+                l(90.0),
+                l(99.0),
+                l(100.0),
+                method,
+                pattern
+            );
+        }
+
+        println!();
+        println!("{} requests to {} endpoints", total, self.endpoints.len());
+    }
+}
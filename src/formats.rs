@@ -0,0 +1,399 @@
+/*
+ * The input and output formats that looker supports.  These tables are what
+ * parse_line() and the -o option use to read and select formats, and the
+ * descriptions here are used both for the -o usage text and by "looker
+ * formats", which lists everything in one place.
+ */
+
+use anyhow::{bail, Result};
+use serde_json::Value;
+
+use crate::{
+    bold, choose_colour, clef, docker, ecs, gcp, glog, goslog, journald,
+    logfmt, pino, syslog, terminal_width, tracing, wrap, zap, Format,
+};
+
+/*
+ * How the lines of an input format are turned into bunyan records.
+ */
+pub enum Reader {
+    /*
+     * Bunyan records are read as they are, and any other lines are not
+     * records at all.
+     */
+    Native,
+    /*
+     * A JSON object is converted to the equivalent bunyan record.
+     */
+    Json(fn(&Value) -> Option<Value>),
+    /*
+     * A line that is not JSON is converted to the equivalent bunyan record.
+     */
+    Text(fn(&str) -> Option<Value>),
+    /*
+     * A prefix is stripped from the line, and the rest read again; see
+     * parse_line().
+     */
+    Prefix,
+}
+
+pub struct InputFormat {
+    pub name: &'static str,
+    /*
+     * How a line is recognised as being in this format.
+     */
+    pub detect: &'static str,
+    pub reader: Reader,
+}
+
+pub struct OutputFormat {
+    pub name: &'static str,
+    pub description: &'static str,
+    /*
+     * Options that only make sense with, or that change the behaviour of,
+     * this output format.
+     */
+    pub options: &'static [&'static str],
+    /*
+     * Checks the properties named as arguments, perhaps supplying a default,
+     * and produces the format to render records in.
+     */
+    pub select: fn(&mut Vec<String>) -> Result<Format>,
+}
+
+/*
+ * Each format is tried in turn, so those that are recognised by more
+ * specific properties must come before those that are less specific.
+ */
+
+pub const INPUTS: &[InputFormat] = &[
+    InputFormat {
+        name: "bunyan",
+        detect: "a JSON object with \"v\": 0 and the \"time\", \"level\", \
+            \"name\", \"hostname\", \"pid\", and \"msg\" properties",
+        reader: Reader::Native,
+    },
+    InputFormat {
+        name: "tracing",
+        detect: "a JSON object with the \"timestamp\", \"level\", and \
+            \"fields\" (or, with flattened events, \"message\") \
+            properties, as written by tracing-subscriber",
+        reader: Reader::Json(tracing::to_bunyan),
+    },
+    InputFormat {
+        name: "pino",
+        detect: "a JSON object with a numeric \"level\" and the \"time\" \
+            property, but no \"v\" (or \"v\": 1); the time may be a \
+            number of seconds, milliseconds, or nanoseconds since the epoch, \
+            as it may also be in bunyan records",
+        reader: Reader::Json(pino::to_bunyan),
+    },
+    InputFormat {
+        name: "slog",
+        detect: "a JSON object with the \"time\", \"msg\", and \
+            \"level\" properties, with the level as a name such as \
+            \"INFO\", as written by Go's log/slog package",
+        reader: Reader::Json(goslog::to_bunyan),
+    },
+    InputFormat {
+        name: "zap",
+        detect: "a JSON object with a numeric \"ts\" property and a \
+            lower-case \"level\" such as \"info\", as written by zap's \
+            production encoder",
+        reader: Reader::Json(zap::to_bunyan),
+    },
+    InputFormat {
+        name: "journald",
+        detect: "a JSON object with the \"__REALTIME_TIMESTAMP\" and \
+            \"MESSAGE\" fields, as written by journalctl -o json",
+        reader: Reader::Json(journald::to_bunyan),
+    },
+    InputFormat {
+        name: "gcp",
+        detect: "a JSON object with a \"severity\" such as \"WARNING\" \
+            and a \"timestamp\" or \"time\", as written for Google \
+            Cloud Logging or exported from it",
+        reader: Reader::Json(gcp::to_bunyan),
+    },
+    InputFormat {
+        name: "ecs",
+        detect: "a JSON object with an \"@timestamp\" and a \
+            \"log.level\" or \"ecs.version\" (whether the names are \
+            dotted or nested), in the Elastic Common Schema",
+        reader: Reader::Json(ecs::to_bunyan),
+    },
+    InputFormat {
+        name: "clef",
+        detect: "a JSON object with the \"@t\" property, and \"@m\" or \
+            \"@mt\", in Serilog's Compact Log Event Format",
+        reader: Reader::Json(clef::to_bunyan),
+    },
+    InputFormat {
+        name: "docker",
+        detect: "a JSON object with just the \"log\", \"stream\", and \
+            \"time\" properties, as written by Docker's json-file logging \
+            driver; the line in \"log\" is read as a record if it is one",
+        reader: Reader::Json(docker::to_bunyan),
+    },
+    InputFormat {
+        name: "cri",
+        detect: "a line prefixed with an RFC 3339 time (and perhaps the \
+            stream and a tag), as in the logs of CRI runtimes or from \
+            kubectl logs --timestamps; the rest of the line is read as a \
+            record if it is one",
+        reader: Reader::Prefix,
+    },
+    InputFormat {
+        name: "logfmt",
+        detect: "a line that is not JSON, but KEY=VALUE pairs, one of them \
+            a time (\"ts\", \"time\", or \"timestamp\")",
+        reader: Reader::Text(logfmt::to_bunyan),
+    },
+    InputFormat {
+        name: "syslog",
+        detect: "a line that is not JSON, but is in the layout of RFC \
+            5424 or of BSD syslog (RFC 3164), with or without the priority",
+        reader: Reader::Text(syslog::to_bunyan),
+    },
+    InputFormat {
+        name: "glog",
+        detect: "a line that is not JSON, but begins with the header of \
+            glog or klog, as in \"I0226 12:34:56.789012 1234 \
+            server.go:123]\"",
+        reader: Reader::Text(glog::to_bunyan),
+    },
+    InputFormat {
+        name: "other",
+        detect: "any other line; passed through when showing all records, \
+            or see --unparsed",
+        reader: Reader::Native,
+    },
+];
+
+/*
+ * Options that affect the display of individual records, which apply to
+ * both of the record-at-a-time formats.
+ */
+const RECORD_OPTIONS: &[&str] = &[
+    "-E/--elapsed",
+    "--elapsed-only",
+    "--tz",
+    "--ditto",
+    "-R/--render",
+    "-u/--unescape",
+    "--unicode",
+    "--backtrace",
+    "--hexdump",
+    "--decode-b64",
+    "--error-chain",
+    "-w/--wrap",
+    "--highlight",
+    "--highlight-match",
+    "-x/--exclude",
+    "--span-tree",
+    "--embedded-json",
+    "--span-times",
+    "--arrival",
+    "--attach",
+    "--dim-context",
+    "-A/-B/--context",
+    "--rare",
+    "--bursts",
+];
+
+pub const OUTPUTS: &[OutputFormat] = &[
+    OutputFormat {
+        name: "short",
+        description: "is the default output format",
+        options: RECORD_OPTIONS,
+        select: |_| Ok(Format::Short),
+    },
+    OutputFormat {
+        name: "long",
+        description: "prints all fields and long timestamps",
+        options: RECORD_OPTIONS,
+        select: |_| Ok(Format::Long),
+    },
+    OutputFormat {
+        name: "bare",
+        description: "prints only the properties named as arguments (the \
+            message if none are named)",
+        options: &["PROPERTY...", "--dim-context"],
+        select: |l| {
+            /*
+             * The most common use of bare mode is to get a list of
+             * messages, so that is what we print if nothing else has been
+             * requested.
+             */
+            if l.is_empty() {
+                l.push("msg".into());
+            }
+            Ok(Format::Bare)
+        },
+    },
+    OutputFormat {
+        name: "msg",
+        description: "prints only the message",
+        options: &["--dim-context"],
+        select: |l| {
+            if !l.is_empty() {
+                bail!("msg mode does not accept properties to print");
+            }
+            l.push("msg".into());
+            Ok(Format::Bare)
+        },
+    },
+    OutputFormat {
+        name: "json",
+        description: "prints each matching record as it was read, one JSON \
+            object per line, for further processing by other tools",
+        options: &[],
+        select: |l| none(l, "json", Format::Json),
+    },
+    OutputFormat {
+        name: "csv",
+        description: "prints the properties named as arguments as CSV, \
+            with a header row",
+        options: &["PROPERTY..."],
+        select: |l| some(l, "csv", Format::Csv),
+    },
+    OutputFormat {
+        name: "tsv",
+        description: "prints the properties named as arguments as TSV, \
+            with a header row",
+        options: &["PROPERTY..."],
+        select: |l| some(l, "tsv", Format::Tsv),
+    },
+    OutputFormat {
+        name: "timeline",
+        description: "groups records by --key and prints each group as a \
+            timeline",
+        options: &["--key"],
+        select: |_| Ok(Format::Timeline),
+    },
+    OutputFormat {
+        name: "access",
+        description: "prints each completed HTTP request as an access log \
+            line: remote address, method, URI, status, bytes, and latency",
+        options: &["--dim-context"],
+        select: |l| none(l, "access", Format::Access),
+    },
+    OutputFormat {
+        name: "clf",
+        description: "prints each completed HTTP request in the Apache \
+            combined log format, for use with tools that analyse access logs",
+        options: &[],
+        select: |l| none(l, "clf", Format::Clf),
+    },
+    OutputFormat {
+        name: "freq",
+        description: "prints a table of the values of the property named \
+            as an argument, from most to least common",
+        options: &["PROPERTY"],
+        select: |l| {
+            if l.len() != 1 {
+                bail!("freq mode needs exactly one field to count");
+            }
+            Ok(Format::Freq)
+        },
+    },
+    OutputFormat {
+        name: "count",
+        description: "prints only the number of matching records",
+        options: &[],
+        select: |l| none(l, "count", Format::Count),
+    },
+    OutputFormat {
+        name: "group",
+        description: "groups the records by the values of the properties \
+            named as arguments, and prints the number in each group, with \
+            the times of the first and last, from largest to smallest",
+        options: &["PROPERTY..."],
+        select: |l| {
+            if l.is_empty() {
+                bail!("group mode needs at least one field to group by");
+            }
+            Ok(Format::Group)
+        },
+    },
+];
+
+/*
+ * Select a format that prints no properties named as arguments.
+ */
+fn none(l: &[String], name: &str, f: Format) -> Result<Format> {
+    if !l.is_empty() {
+        bail!("{} mode does not accept properties to print", name);
+    }
+    Ok(f)
+}
+
+/*
+ * Select a format that prints at least one property named as an argument.
+ */
+fn some(l: &[String], name: &str, f: Format) -> Result<Format> {
+    if l.is_empty() {
+        bail!("{} mode needs at least one property to print", name);
+    }
+    Ok(f)
+}
+
+/*
+ * The usage text for -o.
+ */
+pub fn usage() -> String {
+    let mut s = "output format:\n".to_string();
+    for o in OUTPUTS {
+        s += &format!("- \"{}\" {}\n", o.name, o.description);
+    }
+    s
+}
+
+pub fn run(args: &[String]) -> Result<()> {
+    let mut opts = getopts::Options::new();
+    opts.optflag("", "help", "usage information");
+    opts.optflag("C", "", "force coloured output when not a tty");
+    opts.optflag("N", "", "no terminal formatting");
+
+    let usage = "looker formats [OPTIONS]";
+    let a = match opts.parse(args) {
+        Ok(a) => {
+            if a.opt_present("help") {
+                println!("{}", opts.usage(usage));
+                return Ok(());
+            }
+            a
+        }
+        Err(e) => {
+            eprintln!("{}\nERROR: {}", usage, e);
+            std::process::exit(1);
+        }
+    };
+    if !a.free.is_empty() {
+        eprintln!("{}\nERROR: unexpected arguments", usage);
+        std::process::exit(1);
+    }
+    let colour = choose_colour(a.opt_present("N"), a.opt_present("C"));
+    let width = terminal_width().unwrap_or(80);
+    let w = |s: &str| wrap(s, 15, width);
+
+    println!("{}", bold("INPUT FORMATS", colour));
+    for i in INPUTS {
+        println!("    {:10} {}", i.name, w(i.detect));
+    }
+    println!();
+
+    println!("{}", bold("OUTPUT FORMATS (-o)", colour));
+    for o in OUTPUTS {
+        println!("    {:10} {}", o.name, w(o.description));
+        if !o.options.is_empty() {
+            println!(
+                "    {:10} {}",
+                "",
+                w(&format!("options: {}", o.options.join(", ")))
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+/*
+ * A frequency table of the values of one field among the matching records,
+ * from most to least common; e.g., to see which components are the most
+ * chatty, or which status codes a server returned.
+ */
+
+use std::collections::HashMap;
+
+use crate::{bold, dim, renderers::field, BunyanEntry, Colour};
+
+pub struct Freq {
+    field: String,
+    counts: HashMap<String, u64>,
+    /*
+     * Records that do not have the field are counted, but not as a value.
+     */
+    missing: u64,
+    total: u64,
+}
+
+impl Freq {
+    pub fn new(field: String) -> Freq {
+        Freq { field, counts: Default::default(), missing: 0, total: 0 }
+    }
+
+    pub fn record(&mut self, be: &BunyanEntry) {
+        self.total += 1;
+        match field(be, &self.field) {
+            Some(v) => *self.counts.entry(v).or_default() += 1,
+            None => self.missing += 1,
+        }
+    }
+
+    pub fn report(&self, colour: Colour) {
+        let mut rows = self.counts.iter().collect::<Vec<_>>();
+        rows.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+        println!(
+            "{}",
+            bold(
+                &format!(
+                    "{:>8} {:>6}  {}",
+                    "COUNT",
+                    "%",
+                    self.field.to_uppercase()
+                ),
+                colour
+            )
+        );
+        let pct = |n: u64| 100.0 * n as f64 / self.total as f64;
+        for (v, n) in rows {
+            println!("{:>8} {:>6.2}  {}", n, pct(*n), v);
+        }
+        if self.missing > 0 {
+            println!(
+                "{:>8} {:>6.2}  {}",
+                self.missing,
+                pct(self.missing),
+                dim(&format!("(no {})", self.field), colour)
+            );
+        }
+
+        println!();
+        println!(
+            "{} distinct values in {} records",
+            self.counts.len(),
+            self.total
+        );
+    }
+}
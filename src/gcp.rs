@@ -0,0 +1,91 @@
+/*
+ * Records in the form of Google Cloud Logging, both as written by programs
+ * for its agents to pick up (with "severity", "time" or "timestamp", and
+ * "message" beside the other fields) and as exported from Cloud Logging (in
+ * which the record is a log entry, with the program's fields in
+ * "jsonPayload", or its text in "textPayload").  The name of the log, if
+ * there is one, provides the name.
+ */
+
+use chrono::prelude::*;
+use serde_json::{json, Map, Value};
+
+fn level(s: &str) -> Option<u8> {
+    Some(match s {
+        "DEBUG" => 20,
+        "DEFAULT" | "INFO" | "NOTICE" => 30,
+        "WARNING" => 40,
+        "ERROR" => 50,
+        "CRITICAL" | "ALERT" | "EMERGENCY" => 60,
+        _ => return None,
+    })
+}
+
+/*
+ * The time may also be given as an object, as a protobuf Timestamp.
+ */
+fn time(o: &Map<String, Value>) -> Option<Value> {
+    let t = o.get("timestamp").or_else(|| o.get("time"))?;
+    match t {
+        Value::String(_) => Some(t.clone()),
+        Value::Object(ts) => {
+            let secs = ts.get("seconds")?.as_i64()?;
+            let nanos = ts.get("nanos").and_then(Value::as_u64).unwrap_or(0);
+            let t = DateTime::from_timestamp(secs, nanos.try_into().ok()?)?;
+            Some(t.to_rfc3339_opts(SecondsFormat::AutoSi, true).into())
+        }
+        _ => None,
+    }
+}
+
+pub fn to_bunyan(j: &Value) -> Option<Value> {
+    let o = j.as_object()?;
+    if o.contains_key("v") {
+        return None;
+    }
+    let level = level(o.get("severity")?.as_str()?)?;
+    let time = time(o)?;
+
+    let payload = o.get("jsonPayload").and_then(Value::as_object);
+    let msg = o
+        .get("message")
+        .or_else(|| o.get("textPayload"))
+        .or_else(|| payload?.get("message"))
+        .and_then(Value::as_str)
+        .unwrap_or("");
+    let name = o
+        .get("logName")
+        .and_then(Value::as_str)
+        .map(|l| l.rsplit_once("/logs/").map_or(l, |(_, n)| n))
+        .unwrap_or("-");
+
+    let mut r = json!({
+        "v": 0,
+        "level": level,
+        "name": name,
+        "hostname": "",
+        "pid": 0,
+        "time": time,
+        "msg": msg,
+    });
+    let r_o = r.as_object_mut().unwrap();
+    let extra =
+        payload.into_iter().flatten().filter(|(k, _)| *k != "message").chain(
+            o.iter().filter(|(k, _)| {
+                !matches!(
+                    k.as_str(),
+                    "severity"
+                        | "timestamp"
+                        | "time"
+                        | "message"
+                        | "textPayload"
+                        | "jsonPayload"
+                        | "logName"
+                )
+            }),
+        );
+    for (k, v) in extra {
+        r_o.entry(k.as_str()).or_insert_with(|| v.clone());
+    }
+    Some(r)
+}
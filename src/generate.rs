@@ -0,0 +1,352 @@
+/*
+ * "looker generate": emit synthetic, but plausible, log records.  This is
+ * useful for exercising filters, measuring performance, and demonstrating
+ * looker without access to a real system.
+ */
+
+use std::io::Write;
+
+use anyhow::{bail, Result};
+use chrono::prelude::*;
+use serde_json::{json, Map, Value};
+
+use crate::{parse_time, BunyanLevel};
+
+/*
+ * A small xorshift generator is plenty for synthetic logs, and means the
+ * output for a given seed is stable.
+ */
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len() as u64) as usize]
+    }
+
+    fn uuid(&mut self) -> String {
+        let (a, b) = (self.next(), self.next());
+        format!(
+            "{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}",
+            a >> 32,
+            (a >> 16) & 0xffff,
+            a & 0xfff,
+            (b >> 48) & 0x3fff | 0x8000,
+            b & 0xffff_ffff_ffff,
+        )
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Bunyan,
+    Tracing,
+}
+
+/*
+ * The relative frequency of each level, roughly as one might see from a busy
+ * service logging at debug level.
+ */
+const LEVELS: &[(BunyanLevel, u64)] = &[
+    (BunyanLevel::Trace, 5),
+    (BunyanLevel::Debug, 25),
+    (BunyanLevel::Info, 55),
+    (BunyanLevel::Warn, 10),
+    (BunyanLevel::Error, 4),
+    (BunyanLevel::Fatal, 1),
+];
+
+const COMPONENTS: &[&str] =
+    &["dropshot", "SagaExecutor", "datastore", "BackgroundTasks", "nexus"];
+
+const METHODS: &[&str] = &["GET", "GET", "GET", "POST", "PUT", "DELETE"];
+
+const URIS: &[&str] = &[
+    "/v1/instances",
+    "/v1/disks",
+    "/v1/projects",
+    "/v1/system/hardware/sleds",
+    "/v1/vpcs",
+];
+
+const TASKS: &[&str] =
+    &["dns_config_internal", "external_endpoints", "inventory_collection"];
+
+fn level(rng: &mut Rng) -> &'static BunyanLevel {
+    let total = LEVELS.iter().map(|(_, w)| w).sum::<u64>();
+    let mut n = rng.below(total);
+    for (l, w) in LEVELS {
+        if n < *w {
+            return l;
+        }
+        n -= w;
+    }
+    unreachable!()
+}
+
+/*
+ * Produce a message and a set of fields appropriate for the component.
+ */
+fn event(rng: &mut Rng, comp: &str) -> (String, Map<String, Value>) {
+    let mut f = Map::new();
+    let msg = match comp {
+        "dropshot" => {
+            f.insert("req_id".into(), rng.uuid().into());
+            f.insert("method".into(), (*rng.pick(METHODS)).into());
+            f.insert("uri".into(), (*rng.pick(URIS)).into());
+            f.insert(
+                "remote_addr".into(),
+                format!(
+                    "[fd00:1122:3344:101::{:x}]:{}",
+                    rng.below(64),
+                    40000 + rng.below(20000)
+                )
+                .into(),
+            );
+            if rng.below(2) == 0 {
+                "incoming request".to_string()
+            } else {
+                let status =
+                    *rng.pick(&[200, 200, 200, 201, 204, 400, 404, 500, 503]);
+                f.insert("response_code".into(), status.to_string().into());
+                f.insert(
+                    "latency_us".into(),
+                    (200 + rng.below(900_000)).into(),
+                );
+                "request completed".to_string()
+            }
+        }
+        "SagaExecutor" => {
+            f.insert("saga_id".into(), rng.uuid().into());
+            f.insert(
+                "saga_name".into(),
+                (*rng.pick(&[
+                    "instance-create",
+                    "disk-create",
+                    "snapshot-create",
+                ]))
+                .into(),
+            );
+            f.insert("node_id".into(), rng.below(30).into());
+            format!(
+                "recording saga event: {}",
+                rng.pick(&["started", "succeeded", "failed"])
+            )
+        }
+        "datastore" => {
+            f.insert("elapsed_ms".into(), rng.below(500).into());
+            format!("query returned {} rows", rng.below(200))
+        }
+        "BackgroundTasks" => {
+            f.insert("background_task".into(), (*rng.pick(TASKS)).into());
+            f.insert("iteration".into(), rng.below(1000).into());
+            rng.pick(&["activating", "activation complete"]).to_string()
+        }
+        _ => {
+            format!("sled {} reported {} instances", rng.uuid(), rng.below(40))
+        }
+    };
+    (msg, f)
+}
+
+fn bunyan(
+    t: DateTime<Utc>,
+    l: &BunyanLevel,
+    comp: &str,
+    msg: String,
+    fields: Map<String, Value>,
+) -> Value {
+    let mut r = json!({
+        "v": 0,
+        "level": l.value(),
+        "name": "nexus",
+        "hostname": "oxz_nexus",
+        "pid": 4242,
+        "time": t.to_rfc3339_opts(SecondsFormat::Nanos, true),
+        "msg": msg,
+        "component": comp,
+    });
+    r.as_object_mut().unwrap().extend(fields);
+    r
+}
+
+fn tracing(
+    t: DateTime<Utc>,
+    l: &BunyanLevel,
+    comp: &str,
+    msg: String,
+    mut fields: Map<String, Value>,
+) -> Value {
+    /*
+     * This is the shape produced by the JSON formatter in tracing-subscriber,
+     * with the current span and the full span stack included.
+     */
+    fields.insert("message".into(), msg.into());
+    let span = json!({ "name": comp });
+    json!({
+        "timestamp": t.to_rfc3339_opts(SecondsFormat::Micros, true),
+        "level": match l {
+            BunyanLevel::Fatal | BunyanLevel::Error => "ERROR",
+            BunyanLevel::Warn => "WARN",
+            BunyanLevel::Info => "INFO",
+            BunyanLevel::Debug => "DEBUG",
+            BunyanLevel::Trace | BunyanLevel::Other(_) => "TRACE",
+        },
+        "fields": fields,
+        "target": format!("nexus::{}", comp.to_lowercase()),
+        "span": span,
+        "spans": [ { "name": "nexus" }, span ],
+    })
+}
+
+fn generate(
+    out: &mut dyn Write,
+    rng: &mut Rng,
+    mut t: DateTime<Utc>,
+    count: u64,
+    kind: Kind,
+    malformed: u64,
+    payload: u64,
+) -> std::io::Result<()> {
+    for _ in 0..count {
+        t += chrono::Duration::microseconds(rng.below(200_000) as i64);
+
+        if rng.below(100) < malformed {
+            /*
+             * Produce a mixture of stray plain text and truncated JSON.
+             */
+            let line = if rng.below(2) == 0 {
+                format!(
+                    "thread 'tokio-runtime-worker' panicked at {}",
+                    rng.uuid()
+                )
+            } else {
+                format!(
+                    "{{\"v\":0,\"level\":30,\"msg\":\"truncated {}",
+                    rng.uuid()
+                )
+            };
+            writeln!(out, "{}", line)?;
+            continue;
+        }
+
+        let l = level(rng);
+        let comp = *rng.pick(COMPONENTS);
+        let (msg, mut fields) = event(rng, comp);
+        if payload > 0 {
+            let mut s = String::with_capacity(payload as usize);
+            while (s.len() as u64) < payload {
+                s.push((b'a' + rng.below(26) as u8) as char);
+            }
+            fields.insert("payload".into(), s.into());
+        }
+
+        let r = match kind {
+            Kind::Bunyan => bunyan(t, l, comp, msg, fields),
+            Kind::Tracing => tracing(t, l, comp, msg, fields),
+        };
+        writeln!(out, "{}", r)?;
+    }
+
+    Ok(())
+}
+
+pub fn run(args: &[String]) -> Result<()> {
+    let mut opts = getopts::Options::new();
+    opts.optflag("", "help", "usage information");
+    opts.optopt("n", "count", "number of records (default 1000)", "COUNT");
+    opts.optopt(
+        "",
+        "format",
+        "record format: \"bunyan\" (default) or \"tracing\"",
+        "FORMAT",
+    );
+    opts.optopt(
+        "",
+        "malformed",
+        "percentage of lines that are not valid records (default 0)",
+        "PERCENT",
+    );
+    opts.optopt(
+        "",
+        "payload",
+        "add a field with roughly this many bytes of text to each record",
+        "BYTES",
+    );
+    opts.optopt("", "seed", "seed for the random number generator", "SEED");
+    opts.optopt(
+        "",
+        "start",
+        "timestamp of the first record in RFC 3339 format (default now)",
+        "TIME",
+    );
+
+    let usage = "looker generate [OPTIONS]";
+    let a = match opts.parse(args) {
+        Ok(a) => {
+            if a.opt_present("help") {
+                println!("{}", opts.usage(usage));
+                return Ok(());
+            }
+            a
+        }
+        Err(e) => {
+            eprintln!("{}\nERROR: {}", usage, e);
+            std::process::exit(1);
+        }
+    };
+
+    let num = |name: &str, def: u64| -> Result<u64> {
+        match a.opt_str(name) {
+            Some(s) => match s.parse::<u64>() {
+                Ok(n) => Ok(n),
+                Err(_) => bail!("invalid value for --{name}: {s:?}"),
+            },
+            None => Ok(def),
+        }
+    };
+    let count = num("count", 1000)?;
+    let malformed = num("malformed", 0)?;
+    if malformed > 100 {
+        bail!("--malformed must be a percentage");
+    }
+    let payload = num("payload", 0)?;
+    let kind = match a.opt_str("format").as_deref() {
+        Some("bunyan") | None => Kind::Bunyan,
+        Some("tracing") => Kind::Tracing,
+        Some(other) => bail!("unknown record format {:?}", other),
+    };
+    let t = match a.opt_str("start") {
+        Some(s) => parse_time(&s)?,
+        None => Utc::now(),
+    };
+    /*
+     * The generator state must not be zero, so mix in a constant.
+     */
+    let mut rng = Rng(num("seed", 1)? ^ 0x9e37_79b9_7f4a_7c15);
+
+    let out = std::io::stdout();
+    let mut out = std::io::BufWriter::new(out.lock());
+    match generate(&mut out, &mut rng, t, count, kind, malformed, payload)
+        .and_then(|_| out.flush())
+    {
+        /*
+         * It is normal for the consumer to go away early; e.g., when piping
+         * into head(1).
+         */
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => (),
+        r => r?,
+    }
+
+    Ok(())
+}
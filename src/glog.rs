@@ -0,0 +1,56 @@
+/*
+ * Lines written by glog, and by klog, its fork in Kubernetes, which are
+ * read when a line is not JSON.  Each begins with a header giving the level
+ * (by its initial), the date and time, the thread, and the source location:
+ *
+ *     I0226 12:34:56.789012    1234 server.go:123] Serving on :8080
+ *
+ * The date has no year, which we infer as we do for syslog.
+ */
+
+use serde_json::{json, Value};
+
+use crate::timestamp;
+
+fn level(c: char) -> Option<u8> {
+    Some(match c {
+        'I' => 30,
+        'W' => 40,
+        'E' => 50,
+        'F' => 60,
+        _ => return None,
+    })
+}
+
+pub fn to_bunyan(l: &str) -> Option<Value> {
+    let mut chars = l.chars();
+    let level = level(chars.next()?)?;
+    let (date, rest) = chars.as_str().split_once(' ')?;
+    if date.len() != 4 || !date.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let (time, rest) = rest.split_once(' ')?;
+    let (thread, rest) = rest.trim_start_matches(' ').split_once(' ')?;
+    let (caller, msg) = rest
+        .split_once("] ")
+        .or_else(|| rest.strip_suffix(']').map(|c| (c, "")))?;
+    if caller.contains(' ') {
+        return None;
+    }
+    let t = timestamp::without_year(
+        &format!("{} {}", date, time),
+        "%m%d %H:%M:%S%.f",
+    )?;
+
+    Some(json!({
+        "v": 0,
+        "level": level,
+        "name": "-",
+        "hostname": "",
+        "pid": 0,
+        "time": t.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true),
+        "msg": msg,
+        "thread": thread.parse::<u64>().map_or_else(|_| thread.into(), Value::from),
+        "caller": caller,
+    }))
+}
@@ -0,0 +1,39 @@
+/*
+ * Records written by the JSON handler of Go's log/slog package, which have
+ * the "time", "level", and "msg" properties of a bunyan record, but with
+ * the level as a name, and nothing to identify the program.  The level may
+ * have an offset, as in "INFO+2", which puts it between the standard levels;
+ * we round down to the standard level.
+ */
+
+use serde_json::Value;
+
+fn level(l: &str) -> Option<u8> {
+    let base = l.split(['+', '-']).next()?;
+    Some(match base {
+        "DEBUG" => 20,
+        "INFO" => 30,
+        "WARN" => 40,
+        "ERROR" => 50,
+        _ => return None,
+    })
+}
+
+pub fn to_bunyan(j: &Value) -> Option<Value> {
+    let o = j.as_object()?;
+    if o.contains_key("v") || !o.get("time")?.is_string() {
+        return None;
+    }
+    let level = level(o.get("level")?.as_str()?)?;
+    if !o.get("msg")?.is_string() {
+        return None;
+    }
+
+    let mut r = o.clone();
+    r.insert("v".into(), 0.into());
+    r.insert("level".into(), level.into());
+    r.entry("name").or_insert_with(|| "-".into());
+    r.entry("hostname").or_insert_with(|| "".into());
+    r.entry("pid").or_insert_with(|| 0.into());
+    Some(Value::Object(r))
+}
@@ -0,0 +1,129 @@
+/*
+ * Group the matching records by the values of one or more fields, and list
+ * the groups from largest to smallest, with when each was first and last
+ * seen; e.g., to see which messages are the most common and whether they
+ * are still being logged, in place of "-o bare msg | sort | uniq -c".
+ */
+
+use std::collections::HashMap;
+
+use chrono::prelude::*;
+
+use crate::{bold, dim, renderers::field, BunyanEntry, Colour};
+
+/*
+ * Columns of values wider than this are not padded to line up with one
+ * another, as that would push everything after them too far across.
+ */
+const MAX_WIDTH: usize = 40;
+
+struct Group {
+    count: u64,
+    first: DateTime<Utc>,
+    last: DateTime<Utc>,
+}
+
+pub struct Groups {
+    fields: Vec<String>,
+    groups: HashMap<Vec<Option<String>>, Group>,
+    total: u64,
+}
+
+impl Groups {
+    pub fn new(fields: Vec<String>) -> Groups {
+        Groups { fields, groups: HashMap::new(), total: 0 }
+    }
+
+    pub fn record(&mut self, be: &BunyanEntry) {
+        self.total += 1;
+        let key = self.fields.iter().map(|f| field(be, f)).collect();
+        let g = self.groups.entry(key).or_insert(Group {
+            count: 0,
+            first: be.time,
+            last: be.time,
+        });
+        g.count += 1;
+        g.first = g.first.min(be.time);
+        g.last = g.last.max(be.time);
+    }
+
+    pub fn report(&self, colour: Colour) {
+        let mut rows = self.groups.iter().collect::<Vec<_>>();
+        rows.sort_by(|a, b| {
+            b.1.count.cmp(&a.1.count).then_with(|| a.1.first.cmp(&b.1.first))
+        });
+
+        /*
+         * A record without one of the fields is grouped with the others
+         * that lack it.
+         */
+        let show = |f: &str, v: &Option<String>| match v {
+            Some(v) => v.to_string(),
+            None => dim(&format!("(no {})", f), colour),
+        };
+        let widths = self
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(i, f)| {
+                rows.iter()
+                    .map(|(k, _)| {
+                        k[i].as_ref().map_or(f.len() + 5, |v| v.chars().count())
+                    })
+                    .chain(std::iter::once(f.len()))
+                    .max()
+                    .unwrap_or(0)
+                    .min(MAX_WIDTH)
+            })
+            .collect::<Vec<_>>();
+        let pad = |s: &str, plain: usize, i: usize| {
+            if i + 1 == widths.len() {
+                s.to_string()
+            } else {
+                format!("{}{}", s, " ".repeat(widths[i].saturating_sub(plain)))
+            }
+        };
+
+        let heading = self
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(i, f)| pad(&f.to_uppercase(), f.len(), i))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!(
+            "{}",
+            bold(
+                &format!(
+                    "{:>8}  {:24} {:24} {}",
+                    "COUNT", "FIRST", "LAST", heading
+                ),
+                colour
+            )
+        );
+        for (k, g) in rows {
+            let values = self
+                .fields
+                .iter()
+                .zip(k)
+                .enumerate()
+                .map(|(i, (f, v))| {
+                    let plain =
+                        v.as_ref().map_or(f.len() + 5, |v| v.chars().count());
+                    pad(&show(f, v), plain, i)
+                })
+                .collect::<Vec<_>>()
+                .join("  ");
+            println!(
+                "{:>8}  {:24} {:24} {}",
+                g.count,
+                g.first.to_rfc3339_opts(SecondsFormat::Millis, true),
+                g.last.to_rfc3339_opts(SecondsFormat::Millis, true),
+                values
+            );
+        }
+
+        println!();
+        println!("{} groups of {} records", self.groups.len(), self.total);
+    }
+}
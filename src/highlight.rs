@@ -0,0 +1,135 @@
+/*
+ * Rules from the configuration file that pick out records by the value of a
+ * field, and display them in a particular style; e.g., panics in red, or
+ * chatty components dimmed.
+ */
+
+use anyhow::{anyhow, bail, Result};
+use serde::Deserialize;
+
+use crate::{regex::Regex, BunyanEntry};
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RuleConfig {
+    /*
+     * The field to check, which may be one of the standard fields such as
+     * "msg" or "component".
+     */
+    pub field: String,
+    /*
+     * A regular expression that must match somewhere in the value, or a
+     * string that must equal the value.
+     */
+    pub matches: Option<String>,
+    pub equals: Option<String>,
+    /*
+     * Space-separated style names; e.g., "bold red" or "bg-red".
+     */
+    pub style: String,
+}
+
+enum Test {
+    Matches(Regex),
+    Equals(String),
+}
+
+struct Rule {
+    field: String,
+    test: Test,
+    sgr: String,
+}
+
+/*
+ * Convert style names into an ANSI SGR sequence.
+ */
+fn sgr(style: &str) -> Result<String> {
+    const COLOURS: &[&str] = &[
+        "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    ];
+
+    let mut codes = Vec::new();
+    for word in style.split([' ', ',']).filter(|w| !w.is_empty()) {
+        let (bg, name) = match word.strip_prefix("bg-") {
+            Some(n) => (true, n),
+            None => (false, word),
+        };
+        let (bright, name) = match name.strip_prefix("bright-") {
+            Some(n) => (true, n),
+            None => (false, name),
+        };
+        let code = match (name, bg, bright) {
+            ("bold", false, false) => 1,
+            ("dim", false, false) => 2,
+            ("italic", false, false) => 3,
+            ("underline", false, false) => 4,
+            ("reverse", false, false) => 7,
+            (c, _, _) => {
+                let Some(n) = COLOURS.iter().position(|x| *x == c) else {
+                    bail!("unknown style {:?}", word);
+                };
+                n + match (bg, bright) {
+                    (false, false) => 30,
+                    (true, false) => 40,
+                    (false, true) => 90,
+                    (true, true) => 100,
+                }
+            }
+        };
+        codes.push(code.to_string());
+    }
+    if codes.is_empty() {
+        bail!("empty style");
+    }
+    Ok(format!("\x1b[{}m", codes.join(";")))
+}
+
+pub struct Rules {
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+    pub fn new() -> Result<Rules> {
+        let rules = crate::config::get()
+            .highlight
+            .iter()
+            .map(|r| {
+                let test = match (&r.matches, &r.equals) {
+                    (Some(m), None) => Test::Matches(Regex::new(m)?),
+                    (None, Some(e)) => Test::Equals(e.clone()),
+                    _ => bail!(
+                        "highlight rule for {:?} needs exactly one of \
+                        \"matches\" or \"equals\"",
+                        r.field
+                    ),
+                };
+                let sgr = sgr(&r.style).map_err(|e| {
+                    anyhow!("highlight rule for {:?}: {}", r.field, e)
+                })?;
+                Ok(Rule { field: r.field.clone(), test, sgr })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Rules { rules })
+    }
+
+    /*
+     * Produce the combined style of all rules that apply to this record.
+     */
+    pub fn style(&self, be: &BunyanEntry) -> Option<String> {
+        let style = self
+            .rules
+            .iter()
+            .filter(|r| {
+                let Some(v) = crate::renderers::field(be, &r.field) else {
+                    return false;
+                };
+                match &r.test {
+                    Test::Matches(re) => re.is_match(&v),
+                    Test::Equals(e) => &v == e,
+                }
+            })
+            .map(|r| r.sgr.as_str())
+            .collect::<String>();
+        (!style.is_empty()).then_some(style)
+    }
+}
@@ -0,0 +1,232 @@
+/*
+ * Reading lines of input, keeping track of where each one came from.
+ */
+
+use std::{
+    fs::File,
+    io::{BufRead, Read, Seek, SeekFrom},
+    os::unix::fs::{FileTypeExt, MetadataExt},
+    path::PathBuf,
+};
+
+use anyhow::{anyhow, bail, Result};
+use chrono::prelude::*;
+
+/*
+ * A line of input, along with its (one-based) line number, the byte offset
+ * at which it starts in the original stream, and the time at which we read
+ * it.  When several inputs are merged, "source" is the index of the one the
+ * line came from.
+ */
+pub struct InputLine {
+    pub source: usize,
+    pub number: u64,
+    pub offset: u64,
+    pub arrived: DateTime<Utc>,
+    pub text: String,
+}
+
+pub struct LineReader<R> {
+    r: R,
+    number: u64,
+    offset: u64,
+}
+
+impl<R: BufRead> LineReader<R> {
+    pub fn new(r: R) -> LineReader<R> {
+        LineReader { r, number: 0, offset: 0 }
+    }
+}
+
+impl<R: BufRead> Iterator for LineReader<R> {
+    type Item = Result<InputLine>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut text = String::new();
+        let n = match self.r.read_line(&mut text) {
+            Ok(0) => return None,
+            Ok(n) => n,
+            Err(e) => return Some(Err(e.into())),
+        };
+
+        /*
+         * Strip the line terminator, as BufRead::lines() would.
+         */
+        if text.ends_with('\n') {
+            text.pop();
+            if text.ends_with('\r') {
+                text.pop();
+            }
+        }
+
+        self.number += 1;
+        let line = InputLine {
+            source: 0,
+            number: self.number,
+            offset: self.offset,
+            arrived: Utc::now(),
+            text,
+        };
+        self.offset += n as u64;
+        Some(Ok(line))
+    }
+}
+
+/*
+ * A named pipe that is opened again whenever the writer closes it, so that
+ * we can sit on a FIFO indefinitely while writers come and go.  Opening the
+ * pipe blocks until there is a new writer.
+ */
+pub struct Reopen {
+    path: PathBuf,
+    f: File,
+    /*
+     * Whether the last byte we returned was the end of a line.  If a writer
+     * goes away part way through a line, we end the line for it rather than
+     * joining it to whatever the next writer sends.
+     */
+    eol: bool,
+}
+
+impl Reopen {
+    pub fn new(path: &str) -> Result<Reopen> {
+        let md = std::fs::metadata(path)
+            .map_err(|e| anyhow!("opening file {path:?}: {e}"))?;
+        if !md.file_type().is_fifo() {
+            bail!("{path:?} is not a named pipe, so cannot be reopened");
+        }
+        let f = File::open(path)
+            .map_err(|e| anyhow!("opening file {path:?}: {e}"))?;
+        Ok(Reopen { path: path.into(), f, eol: true })
+    }
+}
+
+impl Read for Reopen {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let n = self.f.read(buf)?;
+            if n > 0 {
+                self.eol = buf[n - 1] == b'\n';
+                return Ok(n);
+            }
+            if !self.eol {
+                buf[0] = b'\n';
+                self.eol = true;
+                return Ok(1);
+            }
+
+            crate::diag::emit(
+                crate::diag::Level::Info,
+                "reopen",
+                &format!("writer closed {:?}; reopening", self.path),
+                &[],
+            );
+            self.f = File::open(&self.path)?;
+        }
+    }
+}
+
+/*
+ * A file that is followed as it grows, in the manner of tail -F: at the end
+ * of the file we wait for more to be written rather than stopping.  If the
+ * file is truncated, we start again from the beginning, and if it is
+ * replaced (e.g., when a log is rotated), we switch to the new file once we
+ * have read all of the old one.
+ */
+pub struct Follow {
+    path: PathBuf,
+    f: File,
+    /*
+     * The identity of the file we have open, and how far into it we are.
+     */
+    id: (u64, u64),
+    pos: u64,
+    eol: bool,
+}
+
+/*
+ * How often to check a file that we have read to the end.
+ */
+const FOLLOW_POLL: std::time::Duration = std::time::Duration::from_millis(250);
+
+impl Follow {
+    pub fn new(path: &str) -> Result<Follow> {
+        let f = File::open(path)
+            .map_err(|e| anyhow!("opening file {path:?}: {e}"))?;
+        let md = f.metadata()?;
+        if !md.is_file() {
+            bail!("{path:?} is not a regular file, so cannot be followed");
+        }
+        Ok(Follow {
+            path: path.into(),
+            f,
+            id: (md.dev(), md.ino()),
+            pos: 0,
+            eol: true,
+        })
+    }
+}
+
+impl Read for Follow {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let n = self.f.read(buf)?;
+            if n > 0 {
+                self.pos += n as u64;
+                self.eol = buf[n - 1] == b'\n';
+                return Ok(n);
+            }
+
+            /*
+             * The file may be missing for a moment while it is rotated; if
+             * so, we keep waiting on the one we have.
+             */
+            let Ok(md) = std::fs::metadata(&self.path) else {
+                std::thread::sleep(FOLLOW_POLL);
+                continue;
+            };
+            if (md.dev(), md.ino()) != self.id {
+                if !self.eol {
+                    buf[0] = b'\n';
+                    self.eol = true;
+                    return Ok(1);
+                }
+                crate::diag::emit(
+                    crate::diag::Level::Info,
+                    "follow",
+                    &format!("{:?} was replaced; reopening", self.path),
+                    &[],
+                );
+                self.f = File::open(&self.path)?;
+                let md = self.f.metadata()?;
+                self.id = (md.dev(), md.ino());
+                self.pos = 0;
+            } else if md.len() < self.pos {
+                crate::diag::emit(
+                    crate::diag::Level::Info,
+                    "follow",
+                    &format!(
+                        "{:?} was truncated; reading from the start",
+                        self.path
+                    ),
+                    &[],
+                );
+                self.f.seek(SeekFrom::Start(0))?;
+                self.pos = 0;
+                if !self.eol {
+                    buf[0] = b'\n';
+                    self.eol = true;
+                    return Ok(1);
+                }
+            } else {
+                std::thread::sleep(FOLLOW_POLL);
+            }
+        }
+    }
+}
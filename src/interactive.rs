@@ -0,0 +1,271 @@
+/*
+ * An interactive loop for exploring a log: the input is read and parsed once
+ * and kept in memory, after which the level, filter, and format can be
+ * changed as often as we like without reading the input again.
+ */
+
+use std::{
+    io::{BufRead, Write},
+    str::FromStr,
+};
+
+use anyhow::{bail, Result};
+
+use crate::{
+    condition::Condition, emit_bare, emit_record, input::InputLine, level,
+    parse_filter, parse_line, BunyanEntry, BunyanLevel, Colour, Columns,
+    Filter, Format, Line, Style,
+};
+
+const HELP: &str = "\
+commands:
+    show [N]              show the matching records (only the last N)
+    count                 count the matching records
+    level [LEVEL]         only records at LEVEL or above (or all records)
+    filter [SCRIPT]       only records for which the rhai SCRIPT is true
+    where [CONDITION]     add a condition such as \"latency_us>500000\" (or
+                          remove all conditions)
+    output FORMAT [PROP]  short, long, bare (with properties), or msg
+    settings              show the current level, filter, and format
+    help                  this message
+    quit";
+
+/*
+ * A record as it is kept in memory: the entry, from which the record is
+ * displayed, and the JSON, which conditions, filters, and bare output
+ * consult.  Both are produced once, when the input is read.
+ */
+struct Cached {
+    be: BunyanEntry,
+    j: serde_json::Value,
+}
+
+pub struct Session<'a> {
+    records: Vec<Cached>,
+    /*
+     * Lines of input that were not records.
+     */
+    unparsed: u64,
+    style: Style<'a>,
+    level: Option<BunyanLevel>,
+    filter: Option<(String, Filter<'static>)>,
+    wheres: Vec<(String, Condition)>,
+    format: Format,
+    lookups: Vec<String>,
+}
+
+impl<'a> Session<'a> {
+    pub fn load(
+        lines: impl Iterator<Item = Result<InputLine>>,
+        style: Style<'a>,
+        level: Option<BunyanLevel>,
+        keep: impl Fn(&BunyanEntry) -> bool,
+        script: Option<String>,
+        wheres: &[String],
+    ) -> Result<Session<'a>> {
+        let filter = script
+            .map(|s| parse_filter(s.clone()).map(|f| (s, f)))
+            .transpose()?;
+        let wheres = wheres
+            .iter()
+            .map(|w| Ok((w.to_string(), Condition::parse(w)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut records = Vec::new();
+        let mut unparsed = 0;
+        for il in lines {
+            match parse_line(&il?.text) {
+                /*
+                 * Records outside the time range, or that do not match -e,
+                 * are not kept at all.
+                 */
+                Line::Record(_, be) if !keep(&be) => (),
+                Line::Record(j, be) => records.push(Cached { be, j }),
+                _ => unparsed += 1,
+            }
+        }
+
+        Ok(Session {
+            records,
+            unparsed,
+            format: style.fmt,
+            lookups: style.lookups.to_vec(),
+            style,
+            level,
+            filter,
+            wheres,
+        })
+    }
+
+    /*
+     * The indices of the records that match the current level, conditions
+     * and filter, along with the number of records on which the filter
+     * failed.
+     */
+    fn matching(&mut self) -> (Vec<usize>, u64) {
+        let mut out = Vec::new();
+        let mut errors = 0;
+        if let Some((_, f)) = &mut self.filter {
+            f.prev = None;
+        }
+        for (i, c) in self.records.iter_mut().enumerate() {
+            if !self.level.map(|l| c.be.level >= l).unwrap_or(true)
+                || !self.wheres.iter().all(|(_, w)| w.matches(&c.j))
+            {
+                continue;
+            }
+            if let Some((_, f)) = &mut self.filter {
+                match f.matches(&mut c.j) {
+                    Ok(true) => (),
+                    Ok(false) => continue,
+                    Err(_) => {
+                        errors += 1;
+                        continue;
+                    }
+                }
+            }
+            out.push(i);
+        }
+        (out, errors)
+    }
+
+    fn count(&mut self, out: &mut dyn Write) -> Result<()> {
+        let (m, errors) = self.matching();
+        writeln!(out, "{} of {} records match", m.len(), self.records.len())?;
+        if errors > 0 {
+            writeln!(out, "the script failed on {} records", errors)?;
+        }
+        Ok(())
+    }
+
+    fn show(&mut self, out: &mut dyn Write, last: Option<usize>) -> Result<()> {
+        let (m, errors) = self.matching();
+        let skip = last.map(|n| m.len().saturating_sub(n)).unwrap_or(0);
+        let style =
+            Style { fmt: self.format, lookups: &self.lookups, ..self.style };
+        let columns = match self.format {
+            Format::Bare => Some(Columns::new(&self.lookups)?),
+            _ => None,
+        };
+        for i in m.into_iter().skip(skip) {
+            let c = &self.records[i];
+            if let Some(columns) = &columns {
+                emit_bare(out, c.j.clone(), columns)?;
+            } else {
+                emit_record(out, &c.be, &style, None, None, None)?;
+            }
+        }
+        if errors > 0 {
+            writeln!(out, "the script failed on {} records", errors)?;
+        }
+        Ok(())
+    }
+
+    fn command(&mut self, out: &mut dyn Write, l: &str) -> Result<bool> {
+        let (cmd, arg) = match l.trim().split_once(char::is_whitespace) {
+            Some((c, a)) => (c, Some(a.trim())),
+            None => (l.trim(), None),
+        };
+
+        match (cmd, arg) {
+            ("", _) => (),
+            ("quit" | "q" | "exit", _) => return Ok(false),
+            ("help" | "?", _) => writeln!(out, "{}", HELP)?,
+            ("show", None) => self.show(out, None)?,
+            ("show", Some(n)) => match n.parse() {
+                Ok(n) => self.show(out, Some(n))?,
+                Err(_) => bail!("invalid number of records {:?}", n),
+            },
+            ("count", _) => self.count(out)?,
+            ("level", l) => {
+                self.level = l.map(BunyanLevel::from_str).transpose()?;
+                self.count(out)?;
+            }
+            ("filter", s) => {
+                self.filter = s
+                    .map(|s| {
+                        parse_filter(s.to_string()).map(|f| (s.to_string(), f))
+                    })
+                    .transpose()?;
+                self.count(out)?;
+            }
+            ("where", None) => {
+                self.wheres.clear();
+                self.count(out)?;
+            }
+            ("where", Some(c)) => {
+                self.wheres.push((c.to_string(), Condition::parse(c)?));
+                self.count(out)?;
+            }
+            ("output", Some(f)) => {
+                let mut words = f.split_whitespace().map(str::to_string);
+                let (format, mut lookups) = match words.next().as_deref() {
+                    Some("short") => (Format::Short, Vec::new()),
+                    Some("long") => (Format::Long, Vec::new()),
+                    Some("bare") => (Format::Bare, words.collect()),
+                    Some("msg") => (Format::Bare, Vec::new()),
+                    _ => {
+                        bail!("interactive output is short, long, bare, or msg")
+                    }
+                };
+                if matches!(format, Format::Bare) && lookups.is_empty() {
+                    lookups.push("msg".into());
+                }
+                Columns::new(&lookups)?;
+                self.format = format;
+                self.lookups = lookups;
+            }
+            ("settings", _) | ("output", None) => self.settings(out)?,
+            (other, _) => bail!("unknown command {:?} (try \"help\")", other),
+        }
+        Ok(true)
+    }
+
+    fn settings(&self, out: &mut dyn Write) -> Result<()> {
+        let format = match self.format {
+            Format::Long => "long",
+            Format::Bare => "bare",
+            _ => "short",
+        };
+        writeln!(out, "output: {} {}", format, self.lookups.join(" "))?;
+        if let Some(l) = &self.level {
+            writeln!(out, "level: {}", level(l, Colour::None))?;
+        }
+        if let Some((s, _)) = &self.filter {
+            writeln!(out, "filter: {}", s)?;
+        }
+        for (w, _) in &self.wheres {
+            writeln!(out, "where: {}", w)?;
+        }
+        Ok(())
+    }
+
+    /*
+     * Read commands until we are told to stop, or there are no more.  A
+     * mistake in a command is reported, and does not end the session.
+     */
+    pub fn run(&mut self, commands: &mut dyn BufRead) -> Result<()> {
+        let mut out = std::io::stdout();
+        writeln!(
+            out,
+            "{} records ({} other lines); \"help\" for commands",
+            self.records.len(),
+            self.unparsed
+        )?;
+        loop {
+            write!(out, "looker> ")?;
+            out.flush()?;
+
+            let mut l = String::new();
+            if commands.read_line(&mut l)? == 0 {
+                writeln!(out)?;
+                return Ok(());
+            }
+            match self.command(&mut out, &l) {
+                Ok(true) => (),
+                Ok(false) => return Ok(()),
+                Err(e) => writeln!(out, "error: {}", e)?,
+            }
+        }
+    }
+}
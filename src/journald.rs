@@ -0,0 +1,68 @@
+/*
+ * Records exported by journalctl -o json, in which every field is a string
+ * (or, where it is not valid UTF-8, an array of bytes).  The time is
+ * "__REALTIME_TIMESTAMP", in microseconds since the epoch, and the level is
+ * the syslog "PRIORITY", from 0 (emergency) to 7 (debug).  The unit (or
+ * failing that, the syslog identifier) provides the name.
+ *
+ * Fields that begin with an underscore are the journal's own (the boot ID,
+ * the command line, the cursor, and so on), which are seldom of interest
+ * and would swamp the fields that the program logged, so we leave them out.
+ */
+
+use serde_json::{json, Map, Value};
+
+use crate::syslog;
+
+fn text(v: &Value) -> Option<String> {
+    match v {
+        Value::String(s) => Some(s.to_string()),
+        Value::Array(a) => {
+            let bytes = a
+                .iter()
+                .map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+                .collect::<Option<Vec<u8>>>()?;
+            Some(String::from_utf8_lossy(&bytes).into_owned())
+        }
+        _ => None,
+    }
+}
+
+pub fn to_bunyan(j: &Value) -> Option<Value> {
+    let o = j.as_object()?;
+    let time = o.get("__REALTIME_TIMESTAMP")?.as_str()?.parse::<i64>().ok()?;
+    let msg = text(o.get("MESSAGE")?)?;
+    let level = match o.get("PRIORITY") {
+        Some(p) => syslog::level(p.as_str()?.parse().ok()?)?,
+        None => 30,
+    };
+
+    let get = |k: &str| o.get(k).and_then(Value::as_str);
+    let name = get("_SYSTEMD_UNIT")
+        .or_else(|| get("SYSLOG_IDENTIFIER"))
+        .unwrap_or("-");
+    let pid = get("_PID").and_then(|p| p.parse::<u64>().ok()).unwrap_or(0);
+
+    let mut r = json!({
+        "v": 0,
+        "level": level,
+        "name": name,
+        "hostname": get("_HOSTNAME").unwrap_or(""),
+        "pid": pid,
+        "time": time,
+        "msg": msg,
+    });
+    let extra = o
+        .iter()
+        .filter(|(k, _)| !k.starts_with('_'))
+        .filter(|(k, _)| !matches!(k.as_str(), "MESSAGE" | "PRIORITY"))
+        .map(|(k, v)| {
+            (k.clone(), text(v).map(Value::from).unwrap_or(v.clone()))
+        })
+        .collect::<Map<_, _>>();
+    let r_o = r.as_object_mut().unwrap();
+    for (k, v) in extra {
+        r_o.entry(k).or_insert(v);
+    }
+    Some(r)
+}
@@ -0,0 +1,125 @@
+/*
+ * A header line that describes the columns of the output, and a key to the
+ * colours of the levels, for the benefit of anyone reading the output who
+ * is not already familiar with it.
+ */
+
+use crate::{bold, config, level, BunyanLevel, Colour, Format};
+
+pub struct Legend<'a> {
+    pub format: Format,
+    pub lookups: &'a [String],
+    pub line_numbers: bool,
+    pub offsets: bool,
+    /*
+     * Whether several files are merged, so that a position includes the
+     * file.
+     */
+    pub merging: bool,
+    pub rare: bool,
+    pub elapsed: bool,
+    /*
+     * Whether the elapsed time is shown in place of the timestamp.
+     */
+    pub elapsed_only: bool,
+    pub arrival: bool,
+}
+
+impl Legend<'_> {
+    fn columns(&self) -> Option<String> {
+        let mut s = String::new();
+        if self.merging && (self.line_numbers || self.offsets) {
+            s += "FILE:";
+        }
+        if self.line_numbers {
+            s += "LINE:";
+        }
+        if self.offsets {
+            s += "OFFSET:";
+        }
+        if !s.is_empty() {
+            s += " ";
+        }
+        if self.rare {
+            s += "RARE ";
+        }
+
+        let time = match self.format {
+            Format::Short => "TIME",
+            Format::Long => "DATE TIME",
+            Format::Bare => {
+                let cols = self
+                    .lookups
+                    .iter()
+                    .map(|l| l.to_uppercase())
+                    .collect::<Vec<_>>();
+                return Some(format!("{}{}", s, cols.join(" ")));
+            }
+            Format::Access => {
+                return Some(s + "REMOTE METHOD URI STATUS BYTES LATENCY");
+            }
+            Format::Clf => {
+                return Some(
+                    s + "HOST IDENT USER [TIME] \"METHOD URI VERSION\" \
+                    STATUS BYTES \"REFERER\" \"USER-AGENT\"",
+                );
+            }
+            /*
+             * The CSV and TSV formats have a header row of their own.
+             */
+            Format::Timeline
+            | Format::Freq
+            | Format::Count
+            | Format::Group
+            | Format::Json
+            | Format::Csv
+            | Format::Tsv
+            | Format::Template => return None,
+        };
+        if self.elapsed_only {
+            s += "ELAPSED";
+        } else {
+            s += time;
+            if self.arrival {
+                s += " [ARRIVED]";
+            }
+            if self.elapsed {
+                s += " ELAPSED";
+            }
+        }
+        s += match self.format {
+            Format::Long => " LEVEL NAME/PID (COMPONENT) on HOST: MESSAGE",
+            _ => " LEVEL NAME (COMPONENT): MESSAGE",
+        };
+        s += "\n    FIELD = VALUE";
+        Some(s)
+    }
+
+    fn levels(&self, colour: Colour) -> String {
+        let mut values = vec![60, 50, 40, 30, 20, 10];
+        values.extend(config::get().levels.iter().map(|l| l.value));
+        values.sort_unstable_by(|a, b| b.cmp(a));
+        values.dedup();
+
+        let levels = values
+            .into_iter()
+            .filter_map(BunyanLevel::from_value)
+            .map(|l| format!("{} ({})", level(&l, colour), l.value()))
+            .collect::<Vec<_>>();
+        format!("levels: {}", levels.join(", "))
+    }
+
+    /*
+     * The legend is not shown for the report modes, which have headings of
+     * their own.
+     */
+    pub fn render(&self, colour: Colour) -> Option<String> {
+        let columns = self.columns()?;
+        let mut s = bold(&columns, colour);
+        if matches!(self.format, Format::Short | Format::Long) {
+            s += "\n";
+            s += &self.levels(colour);
+        }
+        Some(s)
+    }
+}
@@ -0,0 +1,128 @@
+/*
+ * Lines in logfmt, as in:
+ *
+ *     ts=2024-03-01T10:00:00Z level=warn msg="disk almost full" pct=93
+ *
+ * which are read when a line is not JSON.  Each is turned into the
+ * equivalent bunyan record, with the pairs other than the time, level, and
+ * message as fields.  A value that is not quoted is taken to be a number or
+ * a boolean if it looks like one, so that filters can compare it as such,
+ * and a key without a value is taken to be true.
+ */
+
+use serde_json::{json, Map, Value};
+
+use crate::timestamp;
+
+const TIME: &[&str] = &["ts", "time", "timestamp", "t"];
+const LEVEL: &[&str] = &["level", "lvl", "severity"];
+const MSG: &[&str] = &["msg", "message"];
+
+fn level(l: &str) -> Option<u8> {
+    Some(match l.to_ascii_lowercase().as_str() {
+        "trace" => 10,
+        "debug" | "dbug" => 20,
+        "info" => 30,
+        "warn" | "warning" => 40,
+        "error" | "eror" | "err" => 50,
+        "fatal" | "crit" | "critical" | "panic" => 60,
+        _ => return None,
+    })
+}
+
+/*
+ * The pairs of a logfmt line, or None if it is not one.
+ */
+fn pairs(l: &str) -> Option<Vec<(String, Value)>> {
+    let mut out = Vec::new();
+    let mut c = l.trim().chars().peekable();
+    loop {
+        while c.next_if(|c| c.is_whitespace()).is_some() {}
+        if c.peek().is_none() {
+            break;
+        }
+
+        let mut key = String::new();
+        while let Some(ch) = c.next_if(|c| *c != '=' && !c.is_whitespace()) {
+            if ch == '"' {
+                return None;
+            }
+            key.push(ch);
+        }
+        if key.is_empty() {
+            return None;
+        }
+        if c.next_if_eq(&'=').is_none() {
+            out.push((key, Value::Bool(true)));
+            continue;
+        }
+
+        let value = if c.next_if_eq(&'"').is_some() {
+            let mut s = String::new();
+            loop {
+                match c.next()? {
+                    '"' => break,
+                    '\\' => match c.next()? {
+                        'n' => s.push('\n'),
+                        't' => s.push('\t'),
+                        'r' => s.push('\r'),
+                        other => s.push(other),
+                    },
+                    other => s.push(other),
+                }
+            }
+            Value::String(s)
+        } else {
+            let mut s = String::new();
+            while let Some(ch) = c.next_if(|c| !c.is_whitespace()) {
+                s.push(ch);
+            }
+            match serde_json::from_str::<Value>(&s) {
+                Ok(v @ (Value::Number(_) | Value::Bool(_))) => v,
+                _ => Value::String(s),
+            }
+        };
+        out.push((key, value));
+    }
+    Some(out)
+}
+
+fn text(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.to_string(),
+        other => other.to_string(),
+    }
+}
+
+pub fn to_bunyan(l: &str) -> Option<Value> {
+    let mut fields: Map<String, Value> = pairs(l)?.into_iter().collect();
+    let mut take = |keys: &[&str]| keys.iter().find_map(|k| fields.remove(*k));
+
+    /*
+     * Without a time we cannot be confident that this is logfmt at all,
+     * rather than some other text that happens to contain an "=".
+     */
+    let time = take(TIME)?;
+    timestamp::parse(&time)?;
+    let level = match take(LEVEL) {
+        Some(l) => level(&text(&l))?,
+        None => 30,
+    };
+    let msg = take(MSG).map(|m| text(&m)).unwrap_or_default();
+    let name = fields.remove("name").map(|n| text(&n));
+
+    let mut r = json!({
+        "v": 0,
+        "level": level,
+        "name": name.as_deref().unwrap_or("-"),
+        "hostname": "",
+        "pid": 0,
+        "time": time,
+        "msg": msg,
+    });
+    let r_o = r.as_object_mut().unwrap();
+    for (k, v) in fields {
+        r_o.entry(k).or_insert(v);
+    }
+    Some(r)
+}
@@ -1,20 +1,87 @@
 use std::{
+    borrow::Cow,
     collections::BTreeMap,
-    io::{BufRead, BufReader, IsTerminal, Read},
+    io::{BufRead, BufReader, IsTerminal, Read, Write},
     str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 use anyhow::{anyhow, bail, Result};
 use chrono::prelude::*;
 use rhai::{Dynamic, Engine, Scope, AST};
 use serde::Deserialize;
-use serde_repr::Deserialize_repr;
+
+mod access;
+mod backtrace;
+mod bench;
+mod bytes;
+mod clef;
+mod cluster;
+mod compare;
+mod condition;
+mod config;
+mod context;
+mod cri;
+mod decompress;
+mod delimited;
+mod diag;
+mod diff;
+mod docker;
+mod ecs;
+mod embedded;
+mod endpoints;
+mod formats;
+mod freq;
+mod gcp;
+mod generate;
+mod glog;
+mod goslog;
+mod group;
+mod highlight;
+mod input;
+mod interactive;
+mod journald;
+mod legend;
+mod logfmt;
+mod merge;
+mod pair;
+mod pino;
+mod rare;
+mod rate;
+mod record;
+mod regex;
+mod remote;
+mod renderers;
+mod requests;
+mod sagas;
+mod search;
+mod sort;
+mod stats;
+mod summary;
+mod syslog;
+mod tasks;
+mod template;
+mod timeline;
+mod timestamp;
+mod tracing;
+mod zap;
 
 #[derive(Clone, Copy)]
 enum Format {
     Short,
     Long,
     Bare,
+    Timeline,
+    Freq,
+    Count,
+    Group,
+    Access,
+    Clf,
+    Json,
+    Csv,
+    Tsv,
+    Template,
 }
 
 #[derive(Clone, Copy)]
@@ -24,6 +91,27 @@ enum Colour {
     C256,
 }
 
+/*
+ * What to do with lines that are not records we understand.
+ */
+#[derive(Clone, Copy)]
+enum Unparsed {
+    Pass,
+    Drop,
+    Mark,
+}
+
+/*
+ * With --attach, what became of the record to which the lines that follow
+ * it (and are not records themselves) belong.
+ */
+#[derive(Clone, Copy)]
+enum Owner {
+    Shown,
+    Dimmed,
+    Hidden,
+}
+
 #[derive(Deserialize, Debug)]
 struct BunyanEntry {
     v: i64,
@@ -31,6 +119,7 @@ struct BunyanEntry {
     name: String,
     hostname: String,
     pid: u64,
+    #[serde(deserialize_with = "timestamp::deserialize")]
     time: DateTime<Utc>,
     msg: String,
 
@@ -43,15 +132,36 @@ struct BunyanEntry {
     extra: BTreeMap<String, serde_json::Value>,
 }
 
-#[derive(Deserialize_repr, Debug, PartialEq, PartialOrd)]
-#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum BunyanLevel {
-    Fatal = 60,
-    Error = 50,
-    Warn = 40,
-    Info = 30,
-    Debug = 20,
-    Trace = 10,
+    Fatal,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+    /*
+     * A level defined in the configuration file, such as "notice" = 35.
+     */
+    Other(u8),
+}
+
+impl PartialOrd for BunyanLevel {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.value().partial_cmp(&other.value())
+    }
+}
+
+impl<'de> Deserialize<'de> for BunyanLevel {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let n = u8::deserialize(d)?;
+        BunyanLevel::from_value(n).ok_or_else(|| {
+            serde::de::Error::custom(format!("unknown level {}", n))
+        })
+    }
 }
 
 impl FromStr for BunyanLevel {
@@ -62,7 +172,8 @@ impl FromStr for BunyanLevel {
          * We accept either the numeric value or the name (ignoring case) of a
          * level.  We also accept the four column wide truncated version of
          * names as they appear in some output formats; e.g., "DEBG" for Debug
-         * level logs.
+         * level logs.  Additional names may be defined in the configuration
+         * file.
          */
         Ok(match s.trim().to_ascii_lowercase().as_str() {
             "60" | "fatal" | "fata" => BunyanLevel::Fatal,
@@ -71,49 +182,199 @@ impl FromStr for BunyanLevel {
             "30" | "info" => BunyanLevel::Info,
             "20" | "debug" | "debg" => BunyanLevel::Debug,
             "10" | "trace" | "trac" => BunyanLevel::Trace,
-            other => bail!("unknown level {:?}", other),
+            other => {
+                if let Some(l) = config::get().level_by_name(other) {
+                    BunyanLevel::Other(l.value)
+                } else if let Ok(n) = other.parse::<u8>() {
+                    /*
+                     * Any numeric value makes sense as a threshold, even if
+                     * no records have exactly that level.
+                     */
+                    BunyanLevel::Other(n)
+                } else {
+                    bail!("unknown level {:?}", other);
+                }
+            }
         })
     }
 }
 
 impl BunyanLevel {
+    fn from_value(n: u8) -> Option<BunyanLevel> {
+        Some(match n {
+            60 => BunyanLevel::Fatal,
+            50 => BunyanLevel::Error,
+            40 => BunyanLevel::Warn,
+            30 => BunyanLevel::Info,
+            20 => BunyanLevel::Debug,
+            10 => BunyanLevel::Trace,
+            n if config::get().level(n).is_some() => BunyanLevel::Other(n),
+            _ => return None,
+        })
+    }
+
+    fn value(&self) -> u8 {
+        match self {
+            BunyanLevel::Fatal => 60,
+            BunyanLevel::Error => 50,
+            BunyanLevel::Warn => 40,
+            BunyanLevel::Info => 30,
+            BunyanLevel::Debug => 20,
+            BunyanLevel::Trace => 10,
+            BunyanLevel::Other(n) => *n,
+        }
+    }
+
+    /*
+     * The standard level at or below this one, for use when the
+     * configuration does not specify how a custom level should look.
+     */
+    fn standard(&self) -> BunyanLevel {
+        match self.value() {
+            60.. => BunyanLevel::Fatal,
+            50.. => BunyanLevel::Error,
+            40.. => BunyanLevel::Warn,
+            30.. => BunyanLevel::Info,
+            20.. => BunyanLevel::Debug,
+            _ => BunyanLevel::Trace,
+        }
+    }
+
     fn ansi_colour(&self, colour: Colour) -> String {
+        let custom = config::get().level(self.value());
         match colour {
             Colour::None => "".to_string(),
             Colour::C16 => {
-                let n = match self {
-                    BunyanLevel::Fatal => 93,
-                    BunyanLevel::Error => 91,
-                    BunyanLevel::Warn => 95,
-                    BunyanLevel::Info => 96,
-                    BunyanLevel::Debug => 94,
-                    BunyanLevel::Trace => 92,
+                let n = match (self, custom.and_then(|c| c.colour16)) {
+                    (_, Some(n)) => n,
+                    (BunyanLevel::Fatal, _) => 93,
+                    (BunyanLevel::Error, _) => 91,
+                    (BunyanLevel::Warn, _) => 95,
+                    (BunyanLevel::Info, _) => 96,
+                    (BunyanLevel::Debug, _) => 94,
+                    (BunyanLevel::Trace, _) => 92,
+                    (BunyanLevel::Other(_), _) => {
+                        return self.standard().ansi_colour(colour)
+                    }
                 };
                 format!("\x1b[{}m", n)
             }
             Colour::C256 => {
-                let n = match self {
-                    BunyanLevel::Fatal => 190,
-                    BunyanLevel::Error => 160,
-                    BunyanLevel::Warn => 130,
-                    BunyanLevel::Info => 28,
-                    BunyanLevel::Debug => 44,
-                    BunyanLevel::Trace => 69,
+                let n = match (self, custom.and_then(|c| c.colour256)) {
+                    (_, Some(n)) => n,
+                    (BunyanLevel::Fatal, _) => 190,
+                    (BunyanLevel::Error, _) => 160,
+                    (BunyanLevel::Warn, _) => 130,
+                    (BunyanLevel::Info, _) => 28,
+                    (BunyanLevel::Debug, _) => 44,
+                    (BunyanLevel::Trace, _) => 69,
+                    (BunyanLevel::Other(_), _) => {
+                        return self.standard().ansi_colour(colour)
+                    }
                 };
                 format!("\x1b[38;5;{}m", n)
             }
         }
     }
 
-    fn render(&self) -> &'static str {
+    fn render(&self) -> String {
+        let custom = config::get().level(self.value());
+        if let Some(abbrev) = custom.and_then(|c| c.abbrev.as_deref()) {
+            return format!("{:4}", abbrev);
+        }
+
         match self {
-            BunyanLevel::Fatal => "FATA",
-            BunyanLevel::Error => "ERRO",
-            BunyanLevel::Warn => "WARN",
-            BunyanLevel::Info => "INFO",
-            BunyanLevel::Debug => "DEBG",
-            BunyanLevel::Trace => "TRAC",
+            BunyanLevel::Fatal => "FATA".into(),
+            BunyanLevel::Error => "ERRO".into(),
+            BunyanLevel::Warn => "WARN".into(),
+            BunyanLevel::Info => "INFO".into(),
+            BunyanLevel::Debug => "DEBG".into(),
+            BunyanLevel::Trace => "TRAC".into(),
+            BunyanLevel::Other(n) => {
+                /*
+                 * Without an abbreviation, use the first name we have for
+                 * the level, or failing that, its number.
+                 */
+                custom
+                    .and_then(|c| c.names.first())
+                    .map(|n| format!("{:4.4}", n.to_ascii_uppercase()))
+                    .unwrap_or_else(|| format!("L{:<3}", n))
+            }
+        }
+    }
+}
+
+enum Line {
+    Record(serde_json::Value, BunyanEntry),
+    /*
+     * Unrecognised major version in this bunyan record.
+     */
+    UnknownVersion,
+    /*
+     * This record does not contain the minimum required fields.
+     */
+    Invalid,
+    NotJson,
+}
+
+fn parse_line(l: &str) -> Line {
+    let j = match serde_json::from_str::<serde_json::Value>(l) {
+        Ok(j) => j,
+        Err(_) => {
+            /*
+             * A line from the log of a CRI runtime, or from kubectl logs
+             * --timestamps, is read without its prefix.
+             */
+            if let Some((time, rest)) = cri::strip(l) {
+                if let r @ Line::Record(..) = parse_line(rest) {
+                    return r;
+                }
+                if let Some(stamped) = cri::stamp(rest, time) {
+                    if let r @ Line::Record(..) = parse_line(&stamped) {
+                        return r;
+                    }
+                }
+            }
+
+            match formats::INPUTS.iter().find_map(|i| match i.reader {
+                formats::Reader::Text(f) => f(l),
+                _ => None,
+            }) {
+                Some(j) => j,
+                None => return Line::NotJson,
+            }
         }
+    };
+
+    /*
+     * A record may arrive wrapped in the envelope of Docker's logging
+     * driver, in which case we read the record within.
+     */
+    if let Some(l) = docker::payload(&j) {
+        if let r @ Line::Record(..) = parse_line(l) {
+            return r;
+        }
+    }
+
+    /*
+     * Records in other formats are handled as the equivalent bunyan record.
+     */
+    let j = if j.get("v").is_some_and(|v| v == 0) {
+        j
+    } else {
+        formats::INPUTS
+            .iter()
+            .find_map(|i| match i.reader {
+                formats::Reader::Json(f) => f(&j),
+                _ => None,
+            })
+            .unwrap_or(j)
+    };
+
+    match serde_json::from_value::<BunyanEntry>(j.clone()) {
+        Ok(be) if be.v == 0 => Line::Record(j, be),
+        Ok(_) => Line::UnknownVersion,
+        Err(_) => Line::Invalid,
     }
 }
 
@@ -130,49 +391,511 @@ fn bold(input: &str, colour: Colour) -> String {
     s
 }
 
-fn level(bl: BunyanLevel, colour: Colour) -> String {
+fn dim(input: &str, colour: Colour) -> String {
+    if matches!(colour, Colour::None) {
+        input.to_string()
+    } else {
+        format!("\x1b[2m{}\x1b[0m", input)
+    }
+}
+
+fn bright(input: &str, colour: Colour) -> String {
+    if matches!(colour, Colour::None) {
+        input.to_string()
+    } else {
+        format!("\x1b[1;93m{}\x1b[0m", input)
+    }
+}
+
+/*
+ * Mark the parts of a string that match any of the patterns, as a search
+ * does.
+ */
+fn mark(input: &str, patterns: &[regex::Regex], colour: Colour) -> String {
+    if patterns.is_empty() || matches!(colour, Colour::None) {
+        return input.to_string();
+    }
+
+    let mut spans =
+        patterns.iter().flat_map(|p| p.find_all(input)).collect::<Vec<_>>();
+    spans.sort();
+
+    let mut out = String::new();
+    let mut at = 0;
+    for (start, end) in spans {
+        /*
+         * Where matches of different patterns overlap, mark from the end of
+         * the one before.
+         */
+        let start = start.max(at);
+        if start >= end {
+            continue;
+        }
+        out += &input[at..start];
+        out += &format!("\x1b[1;91m{}\x1b[0m", &input[start..end]);
+        at = end;
+    }
+    out += &input[at..];
+    out
+}
+
+fn level(bl: &BunyanLevel, colour: Colour) -> String {
     bold(&format!("{}{}", bl.ansi_colour(colour), bl.render()), colour)
 }
 
-fn emit_bare(j: serde_json::Value, lookups: &Vec<String>) -> Result<()> {
+fn bare_value(v: &serde_json::Value) -> String {
+    match v {
+        serde_json::Value::Null => "null".to_string(),
+        serde_json::Value::Bool(v) => format!("{}", v),
+        serde_json::Value::Number(n) => format!("{}", n),
+        serde_json::Value::String(s) => {
+            let mut out = String::new();
+            for c in s.chars() {
+                if c != '"' && c != '\'' {
+                    out.push_str(&c.escape_default().to_string());
+                } else {
+                    out.push(c);
+                }
+            }
+            out
+        }
+        v @ (serde_json::Value::Array(_) | serde_json::Value::Object(_)) => {
+            v.to_string()
+        }
+    }
+}
+
+/*
+ * Objects and arrays no wider than this are shown as JSON on the same line
+ * as the field name; larger ones are laid out over several lines beneath
+ * it.
+ */
+const NESTED_WIDTH: usize = 60;
+
+enum Column {
+    Field(String),
+    Expr(AST),
+}
+
+/*
+ * The properties to print in bare mode.  Each is either the name of a field
+ * (or a path to a value within one, such as "req.method"), or a simple rhai
+ * expression (e.g., "latency_us / 1000") evaluated with the
+ * fields of the record in scope as variables.
+ */
+struct Columns {
+    engine: Engine,
+    columns: Vec<Column>,
+}
+
+impl Columns {
+    fn new(lookups: &[String]) -> Result<Columns> {
+        let engine = new_engine();
+        let columns = lookups
+            .iter()
+            .map(|l| {
+                if l.split('.').all(|p| {
+                    !p.is_empty()
+                        && p.chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+                }) {
+                    Ok(Column::Field(l.to_string()))
+                } else {
+                    engine.compile_expression(l).map(Column::Expr).map_err(
+                        |e| {
+                            anyhow!(
+                                "compiling expression: {}",
+                                script_error(l, &e, e.position())
+                            )
+                        },
+                    )
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Columns { engine, columns })
+    }
+
+    fn has_exprs(&self) -> bool {
+        self.columns.iter().any(|c| matches!(c, Column::Expr(_)))
+    }
+}
+
+/*
+ * The value of each column for a record, or None where the record lacks the
+ * field (or a field used in the expression).
+ */
+fn column_values(
+    j: serde_json::Value,
+    columns: &Columns,
+) -> Result<Vec<Option<serde_json::Value>>> {
     let o = j.as_object().unwrap();
+
+    let mut scope = Scope::new();
+    if columns.has_exprs() {
+        for (k, v) in o.iter() {
+            scope.push_dynamic(k.as_str(), serde_json::from_value(v.clone())?);
+        }
+        scope.push_dynamic("r", serde_json::from_value(j.clone())?);
+    }
+
     let mut outs = Vec::new();
-    for l in lookups {
-        if let Some(v) = o.get(l) {
-            outs.push(match v {
-                serde_json::Value::Null => "null".to_string(),
-                serde_json::Value::Bool(v) => format!("{}", v),
-                serde_json::Value::Number(n) => format!("{}", n),
-                serde_json::Value::String(s) => {
-                    let mut out = String::new();
-                    for c in s.chars() {
-                        if c != '"' && c != '\'' {
-                            out.push_str(&c.escape_default().to_string());
-                        } else {
-                            out.push(c);
-                        }
+    for c in columns.columns.iter() {
+        match c {
+            Column::Field(l) => {
+                outs.push(renderers::lookup(|k| o.get(k), l).cloned())
+            }
+            Column::Expr(ast) => {
+                let v = match columns
+                    .engine
+                    .eval_ast_with_scope::<Dynamic>(&mut scope, ast)
+                {
+                    Ok(v) => v,
+                    Err(e)
+                        if matches!(
+                            *e,
+                            rhai::EvalAltResult::ErrorVariableNotFound(..)
+                        ) =>
+                    {
+                        Dynamic::UNIT
                     }
-                    out
+                    Err(e) => bail!("expression error: {e}"),
+                };
+                outs.push(if v.is_unit() {
+                    None
+                } else if let Ok(v) = serde_json::to_value(&v) {
+                    Some(v)
+                } else {
+                    Some(serde_json::Value::String(v.to_string()))
+                });
+            }
+        }
+    }
+
+    Ok(outs)
+}
+
+fn emit_bare(
+    out: &mut dyn Write,
+    j: serde_json::Value,
+    columns: &Columns,
+) -> Result<()> {
+    /*
+     * Missing values produce a placeholder, so that the columns still line
+     * up.
+     */
+    let outs = column_values(j, columns)?
+        .iter()
+        .map(|v| v.as_ref().map(bare_value).unwrap_or("-".into()))
+        .collect::<Vec<_>>();
+
+    writeln!(out, "{}", outs.join(" "))?;
+    Ok(())
+}
+
+fn emit_delimited(
+    out: &mut dyn Write,
+    j: serde_json::Value,
+    columns: &Columns,
+    fmt: Format,
+) -> Result<()> {
+    let values = column_values(j, columns)?;
+    writeln!(
+        out,
+        "{}",
+        delimited::row(fmt, values.iter().map(Option::as_ref))
+    )?;
+    Ok(())
+}
+
+/*
+ * Render the time elapsed between two records in a compact form; e.g.,
+ * "+0.443s" or "+2m05.120s".
+ */
+fn elapsed(d: chrono::Duration) -> String {
+    let sign = if d < chrono::Duration::zero() { "-" } else { "+" };
+    let ms = d.num_milliseconds().unsigned_abs();
+    let (h, m, s, ms) =
+        (ms / 3_600_000, (ms / 60_000) % 60, (ms / 1000) % 60, ms % 1000);
+    if h > 0 {
+        format!("{sign}{h}h{m:02}m{s:02}.{ms:03}s")
+    } else if m > 0 {
+        format!("{sign}{m}m{s:02}.{ms:03}s")
+    } else {
+        format!("{sign}{s}.{ms:03}s")
+    }
+}
+
+/*
+ * Options that control how records are displayed in the short and long
+ * formats.
+ */
+struct Style<'a> {
+    colour: Colour,
+    fmt: Format,
+    lookups: &'a [String],
+    /*
+     * Render newlines and tabs in string values as themselves, rather than
+     * as escape sequences.
+     */
+    unescape: bool,
+    /*
+     * Show non-ASCII text in string values as it is, rather than as escape
+     * sequences.
+     */
+    unicode: bool,
+    backtrace: backtrace::Mode,
+    /*
+     * Show fields that contain binary data as a hex dump.
+     */
+    hexdump: bool,
+    /*
+     * Fields to decode from base64 before display.
+     */
+    decode_b64: &'a [String],
+    /*
+     * Split error chains in error fields onto separate lines.
+     */
+    error_chain: bool,
+    /*
+     * Wrap long messages to fit within this many columns.
+     */
+    wrap: Option<usize>,
+    /*
+     * Fields to show in a bright style, so that they stand out.
+     */
+    highlight: &'a [String],
+    /*
+     * Fields not to show.
+     */
+    exclude: &'a [String],
+    /*
+     * Patterns to mark wherever they match in the message or the value of
+     * a field.
+     */
+    marks: &'a [regex::Regex],
+    /*
+     * Show tracing events indented by the depth of their spans.
+     */
+    span_tree: bool,
+    /*
+     * Show JSON embedded in a message pretty-printed beneath it.
+     */
+    embedded_json: bool,
+    zone: Zone,
+    /*
+     * Show the timestamp of each record, rather than only the time elapsed
+     * since the previous one.
+     */
+    time: bool,
+}
+
+/*
+ * Fields that conventionally hold an error, which with slog-error-chain is
+ * the full chain of causes joined by ": ".
+ */
+fn is_error_field(k: &str) -> bool {
+    k == "error" || k == "err" || k.ends_with("_error") || k.ends_with("_err")
+}
+
+/*
+ * Characters that are not control characters, but are invisible or change
+ * the direction of the text around them, and so could make a value appear
+ * to be something that it is not.
+ */
+fn invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200b}'..='\u{200f}'
+            | '\u{202a}'..='\u{202e}'
+            | '\u{2060}'..='\u{2069}'
+            | '\u{feff}'
+    )
+}
+
+/*
+ * Render a string field value.  Quotes are left alone, as the value is not
+ * itself quoted; other special characters are escaped unless we have been
+ * asked to show newlines and tabs as they are.  Non-ASCII text is escaped
+ * too, unless we have been asked to show it, in which case only the
+ * characters that would not be visible are.
+ */
+fn string_value(s: &str, unescape: bool, unicode: bool) -> String {
+    let escape = |l: &str| {
+        let mut o = String::new();
+        for c in l.chars() {
+            match c {
+                '"' | '\'' => o.push(c),
+                '\t' if unescape => o.push(c),
+                c if unicode
+                    && !c.is_ascii()
+                    && !c.is_control()
+                    && !invisible(c) =>
+                {
+                    o.push(c)
                 }
-                serde_json::Value::Array(a) => format!("{:?}", a),
-                serde_json::Value::Object(o) => format!("{:?}", o),
-            });
+                c => o.push_str(&c.escape_default().to_string()),
+            }
+        }
+        o
+    };
+
+    if !unescape {
+        return escape(s);
+    }
+
+    /*
+     * As with multi-line messages, indent the lines after the first so that
+     * they sit beneath the field name and the end of the record remains
+     * obvious.  A trailing newline would only produce an empty line.
+     */
+    s.lines().map(escape).collect::<Vec<_>>().join("\n        ")
+}
+
+/*
+ * The number of columns taken up by a string on the terminal, ignoring any
+ * ANSI escape sequences within it.
+ */
+fn visible_width(s: &str) -> usize {
+    let mut n = 0;
+    let mut escape = false;
+    for c in s.chars() {
+        if escape {
+            escape = !c.is_ascii_alphabetic();
+        } else if c == '\x1b' {
+            escape = true;
+        } else {
+            n += 1;
+        }
+    }
+    n
+}
+
+/*
+ * Wrap a message at word boundaries so that it fits within the terminal,
+ * given that it starts in the column after the record heading.  Continuation
+ * lines are indented to that column so that the columns of the heading remain
+ * clear.  If there is too little room to be useful, the message is left as it
+ * is.
+ */
+fn wrap(msg: &str, indent: usize, width: usize) -> String {
+    let room = width.saturating_sub(indent);
+    if room < 20 || msg.chars().count() <= room {
+        return msg.to_string();
+    }
+
+    let mut lines = vec![String::new()];
+    for word in msg.split(' ') {
+        let cur = lines.last_mut().unwrap();
+        let len = cur.chars().count();
+        if len > 0 && len + 1 + word.chars().count() > room {
+            lines.push(word.to_string());
         } else {
-            outs.push("-".into());
+            if len > 0 {
+                cur.push(' ');
+            }
+            cur.push_str(word);
         }
     }
+    lines.join(&format!("\n{:indent$}", ""))
+}
+
+/*
+ * Determine the width of the terminal, preferring $COLUMNS as the shell
+ * keeps it up to date.
+ */
+fn terminal_width() -> Option<usize> {
+    if let Some(c) = std::env::var("COLUMNS").ok().and_then(|c| c.parse().ok())
+    {
+        return Some(c);
+    }
+
+    let mut ws: libc::winsize = unsafe { std::mem::zeroed() };
+    let r =
+        unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut ws) };
+    (r == 0 && ws.ws_col > 0).then_some(ws.ws_col as usize)
+}
 
-    println!("{}", outs.join(" "));
+/*
+ * Show binary data as a hex dump beneath the field name.
+ */
+fn emit_hexdump(out: &mut dyn Write, b: &[u8], colour: Colour) -> Result<()> {
+    writeln!(out, "{}", dim(&format!("({} bytes)", b.len()), colour))?;
+    for l in bytes::hexdump(b) {
+        writeln!(out, "        {}", l)?;
+    }
     Ok(())
 }
 
+/*
+ * An error object, as serialised by node's bunyan and others, with "name",
+ * "message", and "stack" properties.  We show the name and message in the
+ * colour of errors, with the stack trace beneath and then any other
+ * properties (such as "code").  Returns false, having written nothing, if
+ * the object does not look like an error.
+ */
+fn emit_error_object(
+    out: &mut dyn Write,
+    e: &serde_json::Map<String, serde_json::Value>,
+    style: &Style,
+) -> Result<bool> {
+    let colour = style.colour;
+    let text = |k: &str| e.get(k).and_then(|v| v.as_str());
+    let (name, message, stack) = (text("name"), text("message"), text("stack"));
+    if message.is_none() && stack.is_none() {
+        return Ok(false);
+    }
+
+    let head = match (name, message) {
+        (Some(n), Some(m)) => format!("{}: {}", n, m),
+        (None, Some(m)) => m.to_string(),
+        (Some(n), None) => n.to_string(),
+        (None, None) => String::new(),
+    };
+    let red = BunyanLevel::Error.ansi_colour(colour);
+    writeln!(out, "{}", bold(&format!("{}{}", red, head), colour))?;
+
+    /*
+     * A stack trace from node begins with the name and message, which we
+     * have already shown.
+     */
+    let mut lines = stack.unwrap_or("").lines().peekable();
+    if lines.peek().is_some_and(|l| *l == head) {
+        lines.next();
+    }
+    for l in lines {
+        writeln!(
+            out,
+            "      {}",
+            string_value(l.trim(), false, style.unicode)
+        )?;
+    }
+
+    for (k, v) in e {
+        if matches!(k.as_str(), "name" | "message" | "stack") {
+            continue;
+        }
+        let v = match v {
+            serde_json::Value::String(s) => {
+                string_value(s, false, style.unicode)
+            }
+            other => other.to_string(),
+        };
+        writeln!(out, "      {} = {}", bold(k, colour), v)?;
+    }
+    Ok(true)
+}
+
 fn emit_record(
-    be: BunyanEntry,
-    colour: Colour,
-    fmt: Format,
-    lookups: &Vec<String>,
+    out: &mut dyn Write,
+    be: &BunyanEntry,
+    style: &Style,
+    delta: Option<Option<chrono::Duration>>,
+    ditto: Option<&BTreeMap<String, serde_json::Value>>,
+    arrived: Option<DateTime<Utc>>,
 ) -> Result<()> {
-    let l = level(be.level, colour);
+    let Style { colour, fmt, lookups, .. } = *style;
+    let l = level(&be.level, colour);
     let mut n = bold(&be.name, colour);
     if matches!(fmt, Format::Long) {
         n += &format!("/{}", be.pid);
@@ -183,12 +906,27 @@ fn emit_record(
         }
     };
 
+    let embedded =
+        if style.embedded_json { embedded::find(&be.msg) } else { None };
+
     /*
      * For multi-line messages, indent subsequent lines by 4 spaces, so that
      * they are at least somewhat distinguishable from the next log message.
      */
-    let msg = be
-        .msg
+    let msg = match embedded {
+        Some((before, v, after)) => {
+            let text = [before.trim(), after.trim()]
+                .into_iter()
+                .filter(|t| !t.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+            let pretty = serde_json::to_string_pretty(&v)?;
+            format!("{}\n{}", text, pretty)
+        }
+        None => backtrace::render(&be.msg, style.backtrace)
+            .unwrap_or_else(|| be.msg.clone()),
+    };
+    let msg = msg
         .lines()
         .enumerate()
         .map(|(i, l)| {
@@ -199,47 +937,326 @@ fn emit_record(
         .collect::<Vec<String>>()
         .join("\n");
 
-    match fmt {
-        Format::Short => {
-            let d = be.time.format("%H:%M:%S%.3fZ").to_string();
-            println!("{:13} {} {}: {}", d, l, n, msg);
+    /*
+     * In the span tree, the message of a tracing event is indented by the
+     * depth of the span in which it occurred, and follows the names of the
+     * spans, so that the events of nested operations are grouped together.
+     */
+    let spans =
+        if style.span_tree { tracing::spans(&be.extra) } else { Vec::new() };
+    let msg = if spans.is_empty() {
+        msg
+    } else {
+        let crumb = tracing::path(&be.extra);
+        format!(
+            "{}{} {}",
+            "  ".repeat(spans.len() - 1),
+            dim(&format!("{}:", crumb), colour),
+            msg
+        )
+    };
+
+    /*
+     * If requested, the time elapsed since the previous displayed record
+     * appears in its own column after the timestamp.  The first record has
+     * no predecessor, so the column is left blank.
+     */
+    let sep = if style.time { " " } else { "" };
+    let delta = match delta {
+        Some(Some(d)) => format!("{}{:>10}", sep, elapsed(d)),
+        Some(None) => format!("{}{:>10}", sep, ""),
+        None => "".to_string(),
+    };
+
+    /*
+     * Outside UTC, the short format leaves out the offset to save space;
+     * the long format gives it in full.
+     */
+    let tfmt = match (fmt, style.zone) {
+        (Format::Short, _) => "%H:%M:%S%.3f",
+        (Format::Long, Zone::Utc) => "%Y-%m-%d %H:%M:%S%.3f",
+        (Format::Long, Zone::Local) => "%Y-%m-%d %H:%M:%S%.3f%:z",
+        (
+            Format::Bare
+            | Format::Timeline
+            | Format::Freq
+            | Format::Count
+            | Format::Group
+            | Format::Access
+            | Format::Clf
+            | Format::Json
+            | Format::Csv
+            | Format::Tsv
+            | Format::Template,
+            _,
+        ) => {
+            unreachable!()
+        }
+    };
+    let mut d = show_time(be.time, tfmt, style.zone);
+
+    /*
+     * If requested, the time at which we read the record appears alongside
+     * the time at which it was logged, to expose any buffering between the
+     * producer and us.
+     */
+    if let Some(a) = arrived {
+        d += &format!(
+            " {}",
+            dim(&format!("[{}]", show_time(a, tfmt, style.zone)), colour)
+        );
+    }
+
+    let head = match fmt {
+        Format::Short if !style.time => format!("{} {} {}: ", delta, l, n),
+        Format::Long if !style.time => {
+            format!("{} {} {} on {}: ", delta, l, n, be.hostname)
         }
+        Format::Short => format!("{:13}{} {} {}: ", d, delta, l, n),
         Format::Long => {
-            let d = be.time.format("%Y-%m-%d %H:%M:%S%.3fZ").to_string();
-            println!("{} {} {} on {}: {}", d, l, n, be.hostname, msg);
+            format!("{}{} {} {} on {}: ", d, delta, l, n, be.hostname)
         }
-        Format::Bare => unreachable!(),
-    }
+        Format::Bare
+        | Format::Timeline
+        | Format::Freq
+        | Format::Count
+        | Format::Group
+        | Format::Access
+        | Format::Clf
+        | Format::Json
+        | Format::Csv
+        | Format::Tsv
+        | Format::Template => {
+            unreachable!()
+        }
+    };
 
-    for (k, v) in be.extra.iter() {
-        if !lookups.is_empty() && !lookups.contains(k) {
-            continue;
+    let msg = match style.wrap {
+        Some(width) if !msg.contains('\n') => {
+            wrap(&msg, visible_width(&head), width)
+        }
+        _ => msg,
+    };
+
+    /*
+     * Values of highlighted fields are also highlighted where they appear
+     * in the message.  Very short values would light up all manner of
+     * unrelated text, so they are left alone.
+     */
+    let mut msg = mark(&msg, style.marks, colour);
+    for k in style.highlight {
+        let v = match be.extra.get(k) {
+            Some(serde_json::Value::String(s)) => s.to_string(),
+            Some(v @ serde_json::Value::Number(_)) => v.to_string(),
+            _ => continue,
+        };
+        if v.chars().count() >= 3 {
+            msg = msg.replace(&v, &bright(&v, colour));
         }
+    }
+    writeln!(out, "{}{}", head, msg)?;
 
-        print!("    {} = ", bold(k.as_str(), colour));
+    /*
+     * A lookup may also pick out a value nested within a field, such as
+     * "req.method", which is shown after the fields themselves.
+     */
+    let mut fields = be
+        .extra
+        .iter()
+        .filter(|(k, _)| lookups.is_empty() || lookups.contains(k))
+        .filter(|(k, _)| !style.exclude.contains(k))
+        .filter(|(k, _)| {
+            spans.is_empty() || !matches!(k.as_str(), "span" | "spans")
+        })
+        .map(|(k, v)| (Cow::from(k.as_str()), v))
+        .collect::<Vec<_>>();
+    for l in lookups.iter().filter(|l| !be.extra.contains_key(*l)) {
+        if let Some(v) = renderers::lookup(|k| be.extra.get(k), l) {
+            fields.push((Cow::from(l.as_str()), v));
+        }
+    }
 
-        match v {
-            serde_json::Value::Null => println!("null"),
-            serde_json::Value::Bool(v) => println!("{}", v),
-            serde_json::Value::Number(n) => println!("{}", n),
-            serde_json::Value::String(s) => {
-                let mut out = String::new();
-                for c in s.chars() {
-                    if c != '"' && c != '\'' {
-                        out.push_str(&c.escape_default().to_string());
-                    } else {
-                        out.push(c);
-                    }
-                }
-                println!("{}", out);
+    /*
+     * In the span tree, the fields of each span are shown as fields of the
+     * record, qualified by the name of the span.
+     */
+    for s in spans.iter() {
+        let name = s.get("name").and_then(|n| n.as_str()).unwrap_or("?");
+        for (k, v) in s.iter().filter(|(k, _)| *k != "name") {
+            let k = format!("{}.{}", name, k);
+            if !style.exclude.contains(&k) {
+                fields.push((Cow::from(k), v));
             }
-            serde_json::Value::Array(a) => println!("{:?}", a),
-            serde_json::Value::Object(o) => println!("{:?}", o),
         }
     }
 
-    Ok(())
-}
+    for (k, v) in fields {
+        let k = &*k;
+        if style.highlight.iter().any(|h| h == k) {
+            let v = match v {
+                serde_json::Value::String(s) => {
+                    string_value(s, false, style.unicode)
+                }
+                other => other.to_string(),
+            };
+            writeln!(
+                out,
+                "    {} = {}",
+                bright(k, colour),
+                bright(&v, colour)
+            )?;
+            continue;
+        }
+
+        write!(out, "    {} = ", bold(k, colour))?;
+
+        /*
+         * In ditto mode, values that have not changed since the previous
+         * displayed record are replaced with a dim marker so that only the
+         * changing fields draw the eye.
+         */
+        if ditto.and_then(|prev| prev.get(k)) == Some(v) {
+            writeln!(out, "{}", dim("\"", colour))?;
+            continue;
+        }
+
+        if style.decode_b64.iter().any(|d| d == k) {
+            match v.as_str().and_then(bytes::decode_b64) {
+                /*
+                 * Valid UTF-8 can still be binary data, so only treat it as
+                 * text if it contains no control characters other than
+                 * whitespace.
+                 */
+                Some(b) => match std::str::from_utf8(&b) {
+                    Ok(t)
+                        if t.chars()
+                            .all(|c| !c.is_control() || c.is_whitespace()) =>
+                    {
+                        writeln!(
+                            out,
+                            "{}",
+                            string_value(t, true, style.unicode)
+                        )?
+                    }
+                    _ => emit_hexdump(out, &b, colour)?,
+                },
+                None => writeln!(out, "{} {}", v, dim("(not base64)", colour))?,
+            }
+            continue;
+        }
+
+        if let Some(b) = style.hexdump.then(|| bytes::binary(v)).flatten() {
+            emit_hexdump(out, &b, colour)?;
+            continue;
+        }
+
+        if is_error_field(k) {
+            if let serde_json::Value::Object(e) = v {
+                if emit_error_object(out, e, style)? {
+                    continue;
+                }
+            }
+        }
+
+        match v {
+            serde_json::Value::Null => writeln!(out, "null")?,
+            serde_json::Value::Bool(v) => writeln!(out, "{}", v)?,
+            serde_json::Value::Number(n) => {
+                writeln!(out, "{}", mark(&n.to_string(), style.marks, colour))?
+            }
+            serde_json::Value::String(s) => {
+                /*
+                 * A backtrace is shown as a block beneath the field name,
+                 * whether or not other newlines are being shown as such.
+                 */
+                if let Some(bt) = backtrace::render(s, style.backtrace) {
+                    writeln!(out)?;
+                    for l in bt.lines() {
+                        writeln!(
+                            out,
+                            "      {}",
+                            string_value(l, true, style.unicode)
+                        )?;
+                    }
+                } else if matches!(k, "stacktrace" | "exception")
+                    && s.contains('\n')
+                {
+                    /*
+                     * So too is a Go stack trace, as zap logs it, or an
+                     * exception from CLEF, which includes its stack.
+                     */
+                    writeln!(out)?;
+                    for l in s.lines() {
+                        writeln!(
+                            out,
+                            "      {}",
+                            string_value(l, true, style.unicode)
+                        )?;
+                    }
+                } else if style.error_chain
+                    && is_error_field(k)
+                    && !s.contains('\n')
+                {
+                    /*
+                     * A quoted segment is generally a detail of the cause
+                     * before it, such as the name of an object that was not
+                     * found, rather than a cause in its own right.
+                     */
+                    let mut causes: Vec<String> = Vec::new();
+                    for c in s.split(": ") {
+                        match causes.last_mut() {
+                            Some(l) if c.starts_with(['"', '\'']) => {
+                                *l += ": ";
+                                *l += c;
+                            }
+                            _ => causes.push(c.to_string()),
+                        }
+                    }
+                    writeln!(
+                        out,
+                        "{}",
+                        string_value(&causes[0], false, style.unicode)
+                    )?;
+                    for c in &causes[1..] {
+                        writeln!(
+                            out,
+                            "        {} {}",
+                            dim("caused by:", colour),
+                            string_value(c, false, style.unicode)
+                        )?;
+                    }
+                } else {
+                    writeln!(
+                        out,
+                        "{}",
+                        mark(
+                            &string_value(s, style.unescape, style.unicode),
+                            style.marks,
+                            colour
+                        )
+                    )?
+                }
+            }
+            serde_json::Value::Array(_) | serde_json::Value::Object(_) => {
+                let compact = v.to_string();
+                if compact.chars().count() <= NESTED_WIDTH {
+                    writeln!(out, "{}", mark(&compact, style.marks, colour))?;
+                } else {
+                    writeln!(out)?;
+                    for l in serde_json::to_string_pretty(v)?.lines() {
+                        writeln!(
+                            out,
+                            "      {}",
+                            mark(l, style.marks, colour)
+                        )?;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
 
 fn guess_colour_depth(try_hard: bool) -> Colour {
     match std::env::var("TERM") {
@@ -262,14 +1279,298 @@ fn guess_colour_depth(try_hard: bool) -> Colour {
     }
 }
 
+/*
+ * Parse a duration like "30s", "500ms", "5m", or "1h30m".  A bare number is
+ * interpreted as seconds.
+ */
+fn parse_duration(s: &str) -> Result<Duration> {
+    let t = s.trim();
+    if let Ok(n) = t.parse::<f64>() {
+        if n >= 0.0 && n.is_finite() {
+            return Ok(Duration::from_secs_f64(n));
+        }
+    }
+
+    /*
+     * Spaces between the parts are allowed; e.g., "1 hour 30 minutes".
+     */
+    let t = t.split_whitespace().collect::<String>();
+    let mut total = Duration::ZERO;
+    let mut rest = t.as_str();
+    while !rest.is_empty() {
+        let numlen = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let unitlen = rest[numlen..]
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len() - numlen);
+        let (num, unit) = (&rest[..numlen], &rest[numlen..numlen + unitlen]);
+        let Ok(n) = num.parse::<f64>() else {
+            bail!("invalid duration {:?}", s);
+        };
+        let secs = match unit {
+            "ms" => 0.001,
+            "s" | "sec" | "secs" | "second" | "seconds" => 1.0,
+            "m" | "min" | "mins" | "minute" | "minutes" => 60.0,
+            "h" | "hr" | "hrs" | "hour" | "hours" => 3600.0,
+            "d" | "day" | "days" => 86400.0,
+            _ => bail!("invalid duration {:?}", s),
+        };
+        total += Duration::from_secs_f64(n * secs);
+        rest = &rest[numlen + unitlen..];
+    }
+
+    if total.is_zero() {
+        bail!("invalid duration {:?}", s);
+    }
+    Ok(total)
+}
+
+/*
+ * Parse an absolute timestamp in RFC 3339 format.
+ */
+fn parse_time(s: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s.trim())
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| anyhow!("invalid timestamp {:?}: {e}", s))
+}
+
+/*
+ * Parse a time for --since or --until, which may be absolute, or a duration
+ * before now; e.g., "2h" or "15 minutes ago".
+ */
+fn parse_when(s: &str) -> Result<DateTime<Utc>> {
+    let t = s.trim();
+    if t == "now" {
+        return Ok(Utc::now());
+    }
+    if let Ok(t) = parse_time(t) {
+        return Ok(t);
+    }
+    let ago = t.strip_suffix("ago").unwrap_or(t);
+    match parse_duration(ago)
+        .ok()
+        .and_then(|d| chrono::Duration::from_std(d).ok())
+    {
+        Some(d) => Ok(Utc::now() - d),
+        None => bail!(
+            "invalid time {:?}: expected an RFC 3339 timestamp, or a time \
+            before now such as \"30m\" or \"2 hours ago\"",
+            s
+        ),
+    }
+}
+
+/*
+ * When watching a live stream, the absence of records can be as interesting
+ * as their content.  The watchdog thread wakes up periodically and complains
+ * if no matching record has arrived within the expected window.
+ */
+fn start_watchdog(window: Duration, exit: bool) -> Arc<Mutex<Instant>> {
+    let last = Arc::new(Mutex::new(Instant::now()));
+
+    let wlast = Arc::clone(&last);
+    std::thread::spawn(move || {
+        let mut alerted: Option<Instant> = None;
+        loop {
+            let seen = *wlast.lock().unwrap();
+            let deadline = seen + window;
+            let now = Instant::now();
+            if now < deadline {
+                std::thread::sleep(deadline - now);
+                continue;
+            }
+
+            if alerted != Some(seen) {
+                /*
+                 * A person watching the terminal also gets a bell.
+                 */
+                if !diag::is_json() {
+                    eprint!("\x07");
+                }
+                diag::emit(
+                    diag::Level::Warn,
+                    "no-records",
+                    &format!("no matching records for at least {:?}", window),
+                    &[("window_ms", (window.as_millis() as u64).into())],
+                );
+                if exit {
+                    std::process::exit(2);
+                }
+                alerted = Some(seen);
+            }
+            std::thread::sleep(window.min(Duration::from_secs(1)));
+        }
+    });
+
+    last
+}
+
+fn choose_colour(none: bool, force: bool) -> Colour {
+    if none {
+        Colour::None
+    } else if force || std::io::stdout().is_terminal() {
+        /*
+         * If explicitly enabled, or if we are interactive, try to use colours:
+         */
+        guess_colour_depth(force)
+    } else {
+        Colour::None
+    }
+}
+
+/*
+ * The time zone in which timestamps are shown.
+ */
+#[derive(Clone, Copy)]
+enum Zone {
+    Utc,
+    Local,
+}
+
+/*
+ * Records are logged in UTC, which is also how we show them unless asked
+ * otherwise.  A named zone is found in the system's time zone database;
+ * rather than carry a copy of the database ourselves, we let chrono look it
+ * up as it would if the name were in $TZ.
+ */
+fn choose_zone(name: Option<&str>) -> Result<Zone> {
+    match name {
+        None => Ok(Zone::Utc),
+        Some(n) if n.eq_ignore_ascii_case("utc") => Ok(Zone::Utc),
+        Some(n) if n.eq_ignore_ascii_case("local") => Ok(Zone::Local),
+        Some(n) => {
+            let dir = std::env::var("TZDIR")
+                .unwrap_or_else(|_| "/usr/share/zoneinfo".to_string());
+            if n.starts_with('/')
+                || n.split('/').any(|c| c == "..")
+                || !std::path::Path::new(&dir).join(n).is_file()
+            {
+                bail!("unknown time zone {:?}", n);
+            }
+            std::env::set_var("TZ", n);
+            Ok(Zone::Local)
+        }
+    }
+}
+
+fn show_time(t: DateTime<Utc>, fmt: &str, zone: Zone) -> String {
+    match zone {
+        Zone::Utc => t.format(&format!("{fmt}Z")).to_string(),
+        Zone::Local => t.with_timezone(&Local).format(fmt).to_string(),
+    }
+}
+
 struct Filter<'a> {
     engine: Engine,
     ast: AST,
     scope: Scope<'a>,
+    /*
+     * The previous record given to the script, which is available to it as
+     * "prev".  Keeping a copy of each record is not free, so we only do so
+     * if the script might refer to it.
+     */
+    uses_prev: bool,
+    prev: Option<record::Record>,
 }
 
-fn parse_filter(s: String) -> Result<Filter<'static>> {
+impl Filter<'_> {
+    /*
+     * The record is lent to the script for the duration of the evaluation,
+     * and then returned.
+     */
+    fn matches(&mut self, j: &mut serde_json::Value) -> Result<bool> {
+        let r = record::Record::new(std::mem::take(j));
+
+        self.scope.set_or_push("r", r.clone());
+        self.scope.set_or_push(
+            "prev",
+            self.prev.take().map(Dynamic::from).unwrap_or(Dynamic::UNIT),
+        );
+        let res = self
+            .engine
+            .eval_ast_with_scope::<Dynamic>(&mut self.scope, &self.ast);
+        self.scope.set_or_push("r", ());
+        self.scope.set_or_push("prev", ());
+        *j = r.into_inner();
+        if self.uses_prev {
+            self.prev = Some(record::Record::new(j.clone()));
+        }
+
+        let include = res.map_err(|e| match *e {
+            /*
+             * The reason for stopping a script is more useful than
+             * where it happened to be at the time.
+             */
+            rhai::EvalAltResult::ErrorTerminated(why, _) => {
+                anyhow!("script error: {why}")
+            }
+            e => anyhow!("script error: {e}"),
+        })?;
+
+        if include.is_unit() {
+            /*
+             * If a script returns (), for convenience we treat that as a
+             * request to elide the record.  This makes it possible to do
+             * things like:
+             *
+             *  r.component?.contains("dropshot")
+             */
+            Ok(false)
+        } else if let Ok(include) = include.as_bool() {
+            Ok(include)
+        } else {
+            bail!(
+                "script returned type {:?}, not a bool or ()",
+                include.type_name()
+            );
+        }
+    }
+}
+
+/*
+ * Limits on the work that a script may do for each record, so that a script
+ * with a mistake in it (e.g., an infinite loop) fails quickly rather than
+ * leaving looker apparently hung.
+ */
+const SCRIPT_MAX_OPERATIONS: u64 = 10_000_000;
+const SCRIPT_MAX_TIME: Duration = Duration::from_secs(1);
+
+fn new_engine() -> Engine {
     let mut engine = Engine::new();
+    record::register(&mut engine);
+
+    engine.set_max_operations(SCRIPT_MAX_OPERATIONS);
+    engine.set_max_call_levels(64);
+    engine.set_max_expr_depths(64, 64);
+    engine.set_max_string_size(16 * 1024 * 1024);
+    engine.set_max_array_size(1_000_000);
+    engine.set_max_map_size(1_000_000);
+    /*
+     * The operation count starts again for each evaluation, so we can use
+     * the first operation to note the time at which the script started.
+     * Checking the clock is comparatively expensive, so we only do it
+     * every so often.
+     */
+    let start = std::cell::Cell::new(Instant::now());
+    engine.on_progress(move |ops| {
+        if ops == 1 {
+            start.set(Instant::now());
+        } else if ops % 4096 == 0 && start.get().elapsed() > SCRIPT_MAX_TIME {
+            return Some(
+                format!("script ran for longer than {:?}", SCRIPT_MAX_TIME)
+                    .into(),
+            );
+        }
+        None
+    });
+    /*
+     * Scripts are given only the record, and have no access to files or the
+     * network; there is also no need for them to construct and run other
+     * scripts.
+     */
+    engine.disable_symbol("eval");
+
     engine.register_fn("as_int", |d: Dynamic| -> Dynamic {
         if d.is_unit() {
             /*
@@ -287,15 +1588,93 @@ fn parse_filter(s: String) -> Result<Filter<'static>> {
             Dynamic::UNIT
         }
     });
+    engine
+}
+
+/*
+ * Describe an error in a script, along with the line of the script on which
+ * it occurred and a marker under the offending position.
+ */
+fn script_error(
+    script: &str,
+    e: &dyn std::fmt::Display,
+    pos: rhai::Position,
+) -> String {
+    let (Some(line), Some(col)) = (pos.line(), pos.position()) else {
+        return e.to_string();
+    };
+    let Some(text) = script.lines().nth(line - 1) else {
+        return e.to_string();
+    };
+    format!("{}\n    {}\n    {:>col$}", e, text, "^")
+}
+
+fn parse_filter(s: String) -> Result<Filter<'static>> {
+    let engine = new_engine();
     let scope = Scope::new();
-    let ast = engine
-        .compile_into_self_contained(&scope, s)
-        .map_err(|e| anyhow!("compiling script: {e}"))?;
+    let ast = engine.compile_into_self_contained(&scope, &s).map_err(|e| {
+        anyhow!("compiling script: {}", script_error(&s, &e, e.position()))
+    })?;
+
+    let uses_prev = s.contains("prev");
+    Ok(Filter { engine, ast, scope, uses_prev, prev: None })
+}
+
+/*
+ * The Kafka topic to consume, given as BROKER/TOPIC.
+ */
+#[cfg(feature = "kafka")]
+fn kafka_topic(a: &getopts::Matches) -> Result<Option<(String, String)>> {
+    let Some(spec) = a.opt_str("kafka") else {
+        return Ok(None);
+    };
+    let Some((broker, topic)) = spec.rsplit_once('/') else {
+        bail!("--kafka should be BROKER/TOPIC, not {:?}", spec);
+    };
+    Ok(Some((broker.to_string(), topic.to_string())))
+}
+
+#[cfg(not(feature = "kafka"))]
+fn kafka_topic(_: &getopts::Matches) -> Result<Option<(String, String)>> {
+    Ok(None)
+}
 
-    Ok(Filter { engine, ast, scope })
+/*
+ * Consume a Kafka topic with kcat.  Each message is a record (or several),
+ * and we make sure that each ends a line.
+ */
+fn kafka(broker: &str, topic: &str) -> Result<std::process::Child> {
+    std::process::Command::new("kcat")
+        .args(["-C", "-q", "-u", "-b", broker, "-t", topic, "-f", "%s\n"])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("running kcat to read from Kafka: {e}"))
 }
 
 fn main() -> Result<()> {
+    /*
+     * A program that asked for diagnostics in JSON also needs to be able to
+     * tell why we gave up.
+     */
+    match looker() {
+        Err(e) if diag::is_json() => {
+            diag::emit(diag::Level::Error, "fatal", &format!("{:#}", e), &[]);
+            std::process::exit(1);
+        }
+        res => res,
+    }
+}
+
+fn looker() -> Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.first().map(String::as_str) {
+        Some("bench") => return bench::run(&args[1..]),
+        Some("diff") => return diff::run(&args[1..]),
+        Some("formats") => return formats::run(&args[1..]),
+        Some("generate") => return generate::run(&args[1..]),
+        _ => (),
+    }
+
     let mut opts = getopts::Options::new();
     opts.optflag("", "help", "usage information");
     opts.optflag("C", "", "force coloured output when not a tty");
@@ -308,12 +1687,73 @@ fn main() -> Result<()> {
         "LEVEL",
     );
     opts.optopt(
-        "o",
-        "output",
-        "output format:\n\
-        - \"short\" is the default output format\n\
-        - \"long\" prints all fields and long timestamps\n",
-        "FORMAT",
+        "",
+        "since",
+        "only show records logged at or after this time: either RFC 3339, \
+        or relative to now (e.g., \"30m\" or \"2 hours ago\")",
+        "TIME",
+    );
+    opts.optopt(
+        "",
+        "until",
+        "only show records logged at or before this time, given as for \
+        --since",
+        "TIME",
+    );
+    opts.optopt("o", "output", &formats::usage(), "FORMAT");
+    opts.optopt(
+        "",
+        "format",
+        "print each record on a line of its own layout, in which {FIELD} \
+        is replaced by the value of that field; e.g., \
+        \"{time} {level} {name}: {msg}\"",
+        "TEMPLATE",
+    );
+    opts.optopt(
+        "",
+        "key",
+        "with -o timeline, --pair, or --requests, the field by which to \
+        group records (default \"req_id\")",
+        "FIELD",
+    );
+    opts.optopt(
+        "",
+        "pair",
+        "pair up records whose messages match START and DONE (regular \
+        expressions) and have the same --key, showing each pair as one \
+        record with the duration, and list operations that never finished",
+        "START=DONE",
+    );
+    opts.optflag(
+        "",
+        "unmatched",
+        "with --pair, instead of showing records, list the operations that \
+        never finished and those that finished without a visible start",
+    );
+    opts.optmulti(
+        "e",
+        "regexp",
+        "only show records whose message matches this regular expression \
+        (which may be given more than once, to match any of them)",
+        "PATTERN",
+    );
+    opts.optflag(
+        "v",
+        "invert-match",
+        "with -e, only show records that do not match",
+    );
+    opts.optflag(
+        "",
+        "search-fields",
+        "with -e, match the values of the other fields as well as the \
+        message",
+    );
+    opts.optmulti(
+        "",
+        "highlight-match",
+        "mark the text that matches this regular expression in messages and \
+        field values (as is done for the patterns given with -e)",
+        "PATTERN",
     );
     opts.optopt(
         "c",
@@ -323,169 +1763,1444 @@ fn main() -> Result<()> {
         use `r` to refer to the record under consideration",
         "SCRIPT",
     );
-    opts.optopt("f", "", "read input from a file rather than stdin", "FILE");
-
-    let a = match opts.parse(std::env::args().skip(1)) {
-        Ok(a) => {
-            if a.opt_present("help") {
-                println!("{}", opts.usage(opts.short_usage("looker").trim()));
-                return Ok(());
-            }
-            a
-        }
-        Err(e) => {
-            eprintln!("{}\nERROR: {}", opts.short_usage("looker"), e);
-            std::process::exit(1);
-        }
-    };
-
-    let interactive = std::io::stdout().is_terminal();
-
-    let input: Box<dyn Read> = if let Some(p) = a.opt_str("f") {
-        Box::new(
-            std::fs::File::open(&p)
-                .map_err(|e| anyhow!("opening file {p:?}: {e}"))?,
-        )
-    } else {
-        if std::io::stdin().is_terminal() {
-            /*
-             * It is unlikely that the user intended to run the command without
-             * directing a file or pipe as input.
-             */
-            eprintln!("WARNING: reading from stdin, which is a tty");
-        }
-
-        Box::new(std::io::stdin())
-    };
-    let input = BufReader::new(input);
-    let mut lines = input.lines();
-
-    let mut filter = a.opt_str("c").map(parse_filter).transpose()?;
-
-    let lookups = &a.free;
-
-    let format = match a.opt_str("o").as_deref() {
-        Some("short") | None => Format::Short,
-        Some("long") => Format::Long,
-        Some("bare") => {
-            if lookups.is_empty() {
-                bail!("bare mode requires at least one property to print");
-            }
-
-            Format::Bare
-        }
-        Some(other) => {
-            eprintln!(
-                "{}\nERROR: unknown format type {:?}",
-                opts.short_usage("looker"),
-                other,
-            );
-            std::process::exit(1);
-        }
-    };
-
-    let level =
-        a.opt_str("l").as_deref().map(BunyanLevel::from_str).transpose()?;
-
-    let colour = if a.opt_present("N") {
-        Colour::None
-    } else if a.opt_present("C") || interactive {
-        /*
-         * If explicitly enabled, or if we are interactive, try to use colours:
-         */
-        guess_colour_depth(a.opt_present("C"))
-    } else {
-        Colour::None
-    };
-
-    while let Some(l) = lines.next().transpose()? {
-        match serde_json::from_str::<serde_json::Value>(&l) {
-            Ok(j) => {
-                match serde_json::from_value::<BunyanEntry>(j.clone()) {
-                    Ok(be) if be.v == 0 => {
-                        if let Some(level) = &level {
-                            if &be.level < level {
-                                continue;
-                            }
-                        }
-
-                        if let Some(filter) = &mut filter {
-                            let r: Dynamic = serde_json::from_value(j.clone())?;
-
-                            filter.scope.set_or_push("r", r);
-
-                            let include = filter
-                                .engine
-                                .eval_ast_with_scope::<Dynamic>(
-                                    &mut filter.scope,
-                                    &filter.ast,
-                                )
-                                .map_err(|e| anyhow!("script error: {e}"))?;
-
-                            let include = if include.is_unit() {
-                                /*
-                                 * If a script returns (), for convenience
-                                 * we treat that as a request to elide the
-                                 * record.  This makes it possible to do
-                                 * things like:
-                                 *
-                                 *  r.component?.contains("dropshot")
-                                 */
-                                false
-                            } else if let Ok(include) = include.as_bool() {
-                                include
-                            } else {
-                                bail!(
-                                    "script returned type {:?}, \
-                                    not a bool or ()",
-                                    include.type_name()
-                                );
-                            };
-
-                            if !include {
-                                continue;
-                            }
-                        }
-
-                        if matches!(format, Format::Bare) {
-                            emit_bare(j, lookups)?;
-                        } else {
-                            emit_record(be, colour, format, lookups)?;
-                        }
-                    }
-                    Ok(_) => {
-                        if matches!(format, Format::Bare) || filter.is_some() {
-                            continue;
-                        }
-
-                        /*
-                         * Unrecognised major version in this bunyan record.
-                         */
-                        println!("{}", l);
-                    }
-                    Err(_) => {
-                        if matches!(format, Format::Bare) || filter.is_some() {
-                            continue;
-                        }
-
-                        /*
-                         * This record does not contain the minimum required
-                         * fields.
-                         */
-                        println!("{}", l);
-                    }
-                }
-            }
-            Err(_) => {
-                if matches!(format, Format::Bare) || filter.is_some() {
-                    continue;
-                }
-
-                /*
-                 * Lines that cannot be parsed as JSON are emitted as-is.
-                 */
-                println!("{}", l);
-            }
+    opts.optmulti(
+        "f",
+        "",
+        "read input from a file rather than stdin; this may also be an \
+        s3:// or http(s):// URL.  If more than one file is given, their \
+        records are merged in order of time",
+        "FILE",
+    );
+    opts.optopt(
+        "",
+        "cmd",
+        "run this shell command and read its output, rather than stdin; \
+        looker exits with the status of the command",
+        "COMMAND",
+    );
+    #[cfg(feature = "kafka")]
+    opts.optopt(
+        "",
+        "kafka",
+        "read records from a Kafka topic (using kcat), rather than stdin",
+        "BROKER/TOPIC",
+    );
+    opts.optflag(
+        "",
+        "reopen",
+        "when the -f file is a named pipe, open it again each time the \
+        writer closes it, rather than stopping",
+    );
+    opts.optflag(
+        "F",
+        "follow",
+        "at the end of the -f file, wait for more to be written to it, as \
+        with tail -F",
+    );
+    opts.optflag(
+        "q",
+        "quiet",
+        "drop lines that are not records, rather than passing them through",
+    );
+    opts.optopt(
+        "",
+        "unparsed",
+        "what to do with lines that are not records: pass them through, \
+        drop them, or mark them with a \"?\"; by default they are passed \
+        through unless records are being filtered",
+        "pass|drop|mark",
+    );
+    opts.optopt(
+        "",
+        "config",
+        "read configuration from this file rather than the default \
+        location ($XDG_CONFIG_HOME/looker/config.json)",
+        "FILE",
+    );
+    opts.optflag(
+        "",
+        "sort",
+        "read the entire input and sort records by timestamp before \
+        display; large inputs are sorted using temporary files",
+    );
+    opts.optmulti(
+        "",
+        "where",
+        "only show records where a field compares with a value; e.g., \
+        \"latency_us>500000\" or \"response_code>=500\" (may be repeated, \
+        all conditions must hold)",
+        "CONDITION",
+    );
+    opts.optflag(
+        "E",
+        "elapsed",
+        "show the time elapsed since the previous displayed record; \
+        when filtering, this is the time since the previous match",
+    );
+    opts.optflag(
+        "",
+        "elapsed-only",
+        "show the time elapsed since the previous displayed record (as with \
+        -E) in place of the timestamp",
+    );
+    opts.optflag(
+        "",
+        "ditto",
+        "replace field values that are unchanged since the previous \
+        displayed record with a \" marker",
+    );
+    opts.optflag(
+        "n",
+        "line-numbers",
+        "prefix each record with its line number in the input",
+    );
+    opts.optflag(
+        "",
+        "offsets",
+        "prefix each record with the byte offset of its line in the input",
+    );
+    opts.optflag(
+        "R",
+        "render",
+        "condense records from well-known components (e.g., dropshot \
+        requests and steno sagas) into a single line; additional \
+        renderers may be defined in the config file",
+    );
+    opts.optflag(
+        "u",
+        "unescape",
+        "show newlines and tabs in string field values as they are, \
+        rather than as escape sequences",
+    );
+    opts.optflag(
+        "",
+        "unicode",
+        "show non-ASCII text in string field values as it is, rather than \
+        as escape sequences (control and invisible characters are still \
+        escaped)",
+    );
+    opts.optopt(
+        "",
+        "backtrace",
+        "how to show Rust backtraces found in messages and fields: \
+        full (the default), short (omitting standard library and runtime \
+        frames), or hide",
+        "full|short|hide",
+    );
+    opts.optflag(
+        "",
+        "hexdump",
+        "show fields that contain binary data (arrays of bytes, or base64 \
+        that does not decode to text) as a hex dump",
+    );
+    opts.optmulti(
+        "",
+        "decode-b64",
+        "decode the value of this field from base64, showing the result \
+        as text if it is valid UTF-8 and as a hex dump otherwise",
+        "FIELD",
+    );
+    opts.optflag(
+        "",
+        "error-chain",
+        "show each cause in an error chain (in an \"error\" or \"err\" \
+        field, separated by \": \") on its own line",
+    );
+    opts.optopt(
+        "",
+        "tz",
+        "show timestamps in this time zone: \"local\", \"UTC\" (the \
+        default), or a name such as \"America/New_York\"",
+        "ZONE",
+    );
+    opts.optflagopt(
+        "w",
+        "wrap",
+        "wrap long messages to fit the terminal (or the given number of \
+        columns), indenting continuation lines to line up with the message",
+        "WIDTH",
+    );
+    opts.optflag(
+        "",
+        "attach",
+        "show lines that are not records (such as a stack trace) indented \
+        beneath the record before them, and only if that record is shown",
+    );
+    opts.optflag(
+        "",
+        "dim-context",
+        "rather than hiding records that do not match the level, \
+        conditions, or filter, show them dimmed",
+    );
+    opts.optopt(
+        "A",
+        "after-context",
+        "also show this many records after each match, dimmed",
+        "NUM",
+    );
+    opts.optopt(
+        "B",
+        "before-context",
+        "also show this many records before each match, dimmed",
+        "NUM",
+    );
+    opts.optopt(
+        "",
+        "context",
+        "also show this many records before and after each match (the same \
+        as -A and -B together)",
+        "NUM",
+    );
+    opts.optflagopt(
+        "",
+        "rare",
+        "mark records with a \"!\" if their message template accounts \
+        for less than this percentage (1% by default) of the records seen \
+        so far",
+        "PERCENT",
+    );
+    opts.optflag(
+        "",
+        "arrival",
+        "show the time at which each record was read (in brackets) next to \
+        its timestamp, to reveal delays in live streams",
+    );
+    opts.optmulti(
+        "",
+        "highlight",
+        "show this field, and its value wherever it appears in the \
+        message, in a bright style",
+        "FIELD",
+    );
+    opts.optflag(
+        "",
+        "span-times",
+        "pair the \"new\" and \"close\" events of each tracing span, and add \
+        the time between them to the \"close\" event as duration_ms",
+    );
+    opts.optflagopt(
+        "",
+        "slow-spans",
+        "instead of printing records, list the spans that took the longest \
+        (the 20 slowest by default) from their \"new\" and \"close\" events",
+        "COUNT",
+    );
+    opts.optflag(
+        "",
+        "span-tree",
+        "show the message of each tracing event indented by the depth of \
+        its span, following the names of the spans it is within",
+    );
+    opts.optflag(
+        "",
+        "embedded-json",
+        "show JSON found within a message pretty-printed beneath it, and \
+        make it available to filters as msg_json",
+    );
+    opts.optmulti(
+        "x",
+        "exclude",
+        "do not show this field beneath each record (which may be given \
+        more than once)",
+        "FIELD",
+    );
+    opts.optflag(
+        "",
+        "raw",
+        "print the original input line (dimmed) beneath each record",
+    );
+    opts.optmulti(
+        "",
+        "compare",
+        "instead of printing records, compare counts per component and \
+        message between two time windows; specify twice, each as \
+        START..END in RFC 3339 format, leaving either end open as needed",
+        "WINDOW",
+    );
+    opts.optflag(
+        "",
+        "tasks",
+        "instead of printing records, pair up the activation cycles of \
+        background tasks and report their durations, along with any \
+        that never completed",
+    );
+    opts.optflag(
+        "",
+        "clusters",
+        "instead of printing records, group them by message template \
+        (with numbers, UUIDs, and paths abstracted away) and report the \
+        number of records in each group, with examples",
+    );
+    opts.optflag(
+        "",
+        "sagas",
+        "instead of printing records, reconstruct the execution of each \
+        steno saga from its node events",
+    );
+    opts.optflag(
+        "",
+        "endpoints",
+        "instead of printing records, summarise completed dropshot \
+        requests by endpoint with error rates and latency percentiles",
+    );
+    opts.optflag(
+        "",
+        "stats",
+        "instead of printing records, count them by level and by source, \
+        and report the time they cover and the lines that were not records",
+    );
+    opts.optflag(
+        "",
+        "requests",
+        "instead of printing records, summarise each request as it \
+        completes, following it by --key: its start, duration, status, and \
+        the number of records logged for it",
+    );
+    opts.optopt(
+        "",
+        "rate",
+        "instead of printing records, report the number of records in \
+        each interval of this length (e.g., \"10s\"), flagging bursts",
+        "INTERVAL",
+    );
+    opts.optopt(
+        "",
+        "bursts",
+        "print a marker when the number of records in an interval of this \
+        length rises well above the usual rate",
+        "INTERVAL",
+    );
+    opts.optopt(
+        "",
+        "burst-factor",
+        "with --rate or --bursts, how many times the usual rate makes a \
+        burst (default 3)",
+        "FACTOR",
+    );
+    opts.optflag(
+        "",
+        "per-component",
+        "with --rate or --bursts, consider the rate of each component \
+        separately",
+    );
+    opts.optopt(
+        "",
+        "expect-within",
+        "print a warning if no matching record arrives within this \
+        window (e.g., \"30s\") while reading a live stream",
+        "DURATION",
+    );
+    opts.optflag(
+        "",
+        "expect-exit",
+        "exit with status 2 if the --expect-within window elapses",
+    );
+
+    opts.optflag(
+        "",
+        "strict",
+        "fail if any line is not a record, as a check that a program logs \
+        nothing else",
+    );
+    opts.optflag(
+        "",
+        "ignore-script-errors",
+        "report records on which the -c script fails on stderr and carry \
+        on, rather than stopping",
+    );
+    opts.optflag(
+        "",
+        "check",
+        "check the options and any filter script or expressions, and exit \
+        without reading input",
+    );
+    opts.optflag(
+        "",
+        "interactive",
+        "read the whole input into memory, then prompt for commands to \
+        change the level, filter, and format and show matching records",
+    );
+    opts.optflag(
+        "",
+        "footer",
+        "end the output with the number of records read, displayed, and \
+        filtered out, and the time span that they cover",
+    );
+    opts.optflag(
+        "",
+        "summary",
+        "print only the summary that --footer would print, not the records",
+    );
+    opts.optflag(
+        "",
+        "legend",
+        "begin the output with a line that describes the columns, and the \
+        colours of the levels",
+    );
+    opts.optflagmulti(
+        "",
+        "verbose",
+        "report more about what looker is doing on stderr; e.g., lines \
+        that are not records (may be repeated)",
+    );
+    opts.optopt(
+        "",
+        "diagnostics",
+        "format of looker's own messages on stderr: \"text\" (the \
+        default) or \"json\", with one object per line",
+        "FORMAT",
+    );
+
+    let a = match opts.parse(&args) {
+        Ok(a) => {
+            if a.opt_present("help") {
+                println!("{}", opts.usage(opts.short_usage("looker").trim()));
+                return Ok(());
+            }
+            a
+        }
+        Err(e) => {
+            eprintln!("{}\nERROR: {}", opts.short_usage("looker"), e);
+            std::process::exit(1);
+        }
+    };
+
+    diag::init(a.opt_count("verbose"), a.opt_str("diagnostics").as_deref())?;
+    config::init(a.opt_str("config").as_deref())?;
+    let zone = choose_zone(
+        a.opt_str("tz").as_deref().or(config::get().tz.as_deref()),
+    )?;
+
+    let mut sources = vec!["f", "cmd"];
+    if cfg!(feature = "kafka") {
+        sources.push("kafka");
+    }
+    if sources.iter().filter(|o| a.opt_present(o)).count() > 1 {
+        bail!("only one of -f, --cmd, and --kafka may be used");
+    }
+    let paths = a.opt_strs("f");
+    if paths.is_empty() && a.opt_present("reopen") {
+        bail!("--reopen requires -f");
+    }
+    if paths.is_empty() && a.opt_present("F") {
+        bail!("-F requires -f");
+    }
+    if paths.len() > 1 && (a.opt_present("reopen") || a.opt_present("F")) {
+        bail!("--reopen and -F can only be used with a single -f file");
+    }
+    if a.opt_present("reopen") && a.opt_present("F") {
+        bail!("--reopen and -F cannot be used together");
+    }
+    if a.opt_present("F") && paths.iter().any(|p| remote::is_remote(p)) {
+        bail!("-F cannot be used with a URL");
+    }
+
+    let mut filter = a.opt_str("c").map(parse_filter).transpose()?;
+
+    let mut lookups = a.free.clone();
+
+    let template = a.opt_str("format");
+    let format = match a.opt_str("o").as_deref() {
+        _ if template.is_some() => {
+            if a.opt_present("o") || !lookups.is_empty() {
+                bail!("--format cannot be used with -o or properties");
+            }
+
+            Format::Template
+        }
+        o => {
+            let name = o.unwrap_or("short");
+            match formats::OUTPUTS.iter().find(|f| f.name == name) {
+                Some(f) => (f.select)(&mut lookups)?,
+                None => {
+                    eprintln!(
+                        "{}\nERROR: unknown format type {:?} \
+                        (see \"looker formats\")",
+                        opts.short_usage("looker"),
+                        name,
+                    );
+                    std::process::exit(1);
+                }
+            }
+        }
+    };
+
+    let level =
+        a.opt_str("l").as_deref().map(BunyanLevel::from_str).transpose()?;
+    let since = a.opt_str("since").as_deref().map(parse_when).transpose()?;
+    let until = a.opt_str("until").as_deref().map(parse_when).transpose()?;
+    if let (Some(s), Some(u)) = (since, until) {
+        if s > u {
+            bail!("--since must not be later than --until");
+        }
+    }
+    let in_range = |t: DateTime<Utc>| {
+        since.map(|s| t >= s).unwrap_or(true)
+            && until.map(|u| t <= u).unwrap_or(true)
+    };
+
+    let search = match a.opt_strs("e") {
+        p if p.is_empty() => {
+            if a.opt_present("v") || a.opt_present("search-fields") {
+                bail!("-v and --search-fields require -e");
+            }
+            None
+        }
+        p => Some(search::Search::new(
+            &p,
+            a.opt_present("search-fields"),
+            a.opt_present("v"),
+        )?),
+    };
+
+    /*
+     * What a search found is marked in the output, unless it was looking
+     * for records without it.
+     */
+    let mut marks = a.opt_strs("highlight-match");
+    if !a.opt_present("v") {
+        marks.extend(a.opt_strs("e"));
+    }
+    let marks = marks
+        .iter()
+        .map(|p| {
+            regex::Regex::new(p).map_err(|e| anyhow!("pattern {:?}: {}", p, e))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let lookups = &lookups;
+    let columns = Columns::new(
+        if matches!(format, Format::Bare | Format::Csv | Format::Tsv) {
+            lookups
+        } else {
+            &[]
+        },
+    )?;
+
+    let colour = choose_colour(a.opt_present("N"), a.opt_present("C"));
+
+    let expect_within = a
+        .opt_str("expect-within")
+        .as_deref()
+        .map(parse_duration)
+        .transpose()?;
+    if expect_within.is_none() && a.opt_present("expect-exit") {
+        bail!("--expect-exit requires --expect-within");
+    }
+
+    let elapsed_only = a.opt_present("elapsed-only");
+    let show_elapsed = a.opt_present("E") || elapsed_only;
+    let mut prev_time: Option<DateTime<Utc>> = None;
+    let ditto = a.opt_present("ditto");
+    let raw = a.opt_present("raw");
+    let arrival = a.opt_present("arrival");
+    if arrival && elapsed_only {
+        bail!("--arrival cannot be used with --elapsed-only");
+    }
+    let line_numbers = a.opt_present("line-numbers");
+    let offsets = a.opt_present("offsets");
+    let decode_b64 = a.opt_strs("decode-b64");
+    let highlight = a.opt_strs("highlight");
+    let exclude = a.opt_strs("x");
+    let wrap = match a.opt_default("wrap", "") {
+        Some(w) if w.is_empty() => terminal_width(),
+        Some(w) => {
+            Some(w.parse().map_err(|_| anyhow!("invalid wrap width {:?}", w))?)
+        }
+        None => None,
+    };
+    let embedded_json = a.opt_present("embedded-json");
+    let style = Style {
+        colour,
+        fmt: format,
+        lookups,
+        unescape: a.opt_present("unescape"),
+        unicode: a.opt_present("unicode"),
+        backtrace: a
+            .opt_str("backtrace")
+            .as_deref()
+            .unwrap_or("full")
+            .parse()?,
+        hexdump: a.opt_present("hexdump"),
+        decode_b64: &decode_b64,
+        error_chain: a.opt_present("error-chain"),
+        wrap,
+        highlight: &highlight,
+        exclude: &exclude,
+        marks: &marks,
+        span_tree: a.opt_present("span-tree"),
+        embedded_json,
+        zone,
+        time: !elapsed_only,
+    };
+    let renderers = a.opt_present("render").then(renderers::Registry::new);
+    let mut prev_extra: Option<BTreeMap<String, serde_json::Value>> = None;
+
+    let mut compare = if a.opt_present("compare") {
+        Some(compare::Compare::new(&a.opt_strs("compare"))?)
+    } else {
+        None
+    };
+
+    let mut tasks = a.opt_present("tasks").then(tasks::Tasks::new);
+    let mut clusters = a.opt_present("clusters").then(cluster::Clusters::new);
+    let mut sagas = a.opt_present("sagas").then(sagas::Sagas::new);
+    let mut endpoints =
+        a.opt_present("endpoints").then(endpoints::Endpoints::new);
+    let mut stats = a.opt_present("stats").then(stats::Stats::new);
+    let rates = |opt: &str| {
+        a.opt_str(opt)
+            .map(|i| {
+                rate::Rates::new(
+                    parse_duration(&i)?,
+                    a.opt_str("burst-factor").as_deref(),
+                    a.opt_present("per-component"),
+                )
+            })
+            .transpose()
+    };
+    let mut rate_report = rates("rate")?.map(rate::RateReport::new);
+    let mut bursts = rates("bursts")?.map(rate::BurstMarkers::new);
+
+    /*
+     * The report modes replace the display of records, so only one of them
+     * can be used at a time.
+     */
+    let mut timeline = match (format, a.opt_str("key")) {
+        (Format::Timeline, key) => Some(timeline::Timeline::new(
+            key.unwrap_or_else(|| "req_id".to_string()),
+        )),
+        (_, Some(_))
+            if !a.opt_present("pair") && !a.opt_present("requests") =>
+        {
+            bail!("--key is only used with -o timeline, --pair, or --requests")
+        }
+        _ => None,
+    };
+    let slow_spans = match a.opt_default("slow-spans", "20") {
+        Some(n) => Some(
+            n.parse::<usize>()
+                .map_err(|_| anyhow!("invalid number of spans {:?}", n))?,
+        ),
+        None => None,
+    };
+    let mut span_times = (a.opt_present("span-times") || slow_spans.is_some())
+        .then(|| tracing::SpanTimes::new(slow_spans.is_some()));
+    let mut requests = a.opt_present("requests").then(|| {
+        requests::Requests::new(
+            a.opt_str("key").unwrap_or_else(|| "req_id".to_string()),
+            colour,
+        )
+    });
+
+    let mut matching = matches!(format, Format::Count).then_some(0u64);
+    let mut groups = match format {
+        Format::Group => Some(group::Groups::new(lookups.clone())),
+        _ => None,
+    };
+    let mut freq = match format {
+        Format::Freq => Some(freq::Freq::new(lookups[0].clone())),
+        _ => None,
+    };
+
+    let unmatched = a.opt_present("unmatched");
+    if unmatched && !a.opt_present("pair") {
+        bail!("--unmatched requires --pair");
+    }
+
+    let report = [
+        timeline.is_some(),
+        compare.is_some(),
+        tasks.is_some(),
+        clusters.is_some(),
+        rate_report.is_some(),
+        sagas.is_some(),
+        unmatched,
+        freq.is_some(),
+        matching.is_some(),
+        groups.is_some(),
+        endpoints.is_some(),
+        requests.is_some(),
+        slow_spans.is_some(),
+        stats.is_some(),
+    ];
+    if report.iter().filter(|r| **r).count() > 1 {
+        bail!(
+            "only one of -o timeline, -o freq, -o count, -o group, --compare, --tasks, \
+            --clusters, --rate, --sagas, --endpoints, --requests, \
+            --slow-spans, --stats, and --unmatched may be used"
+        );
+    }
+    let report = report.contains(&true);
+
+    /*
+     * When following a file, there is no end of the input at which to
+     * print a report or sort the records.
+     */
+    if a.opt_present("F") {
+        if report || a.opt_present("summary") {
+            bail!("-F cannot be used with a report mode or --summary");
+        }
+        if a.opt_present("sort") || a.opt_present("interactive") {
+            bail!("-F cannot be used with --sort or --interactive");
+        }
+    }
+
+    let mut pairs = a
+        .opt_str("pair")
+        .map(|p| {
+            pair::Pairs::new(
+                &p,
+                a.opt_str("key").unwrap_or_else(|| "req_id".to_string()),
+            )
+        })
+        .transpose()?;
+    if pairs.is_some() && report && !unmatched {
+        bail!("--pair cannot be used with a report mode");
+    }
+
+    let wheres = a
+        .opt_strs("where")
+        .iter()
+        .map(|s| condition::Condition::parse(s))
+        .collect::<Result<Vec<_>>>()?;
+
+    /*
+     * With --summary, only the footer is printed.
+     */
+    let summary_only = a.opt_present("summary");
+    if summary_only && report {
+        bail!("--summary cannot be used with a report mode");
+    }
+    let dim_context = a.opt_present("dim-context") && !summary_only;
+
+    /*
+     * As with grep, -A and -B take precedence over --context.
+     */
+    let count = |name: &str| {
+        a.opt_str(name)
+            .or_else(|| a.opt_str("context"))
+            .map(|n| {
+                n.parse::<usize>()
+                    .map_err(|_| anyhow!("invalid context length {:?}", n))
+            })
+            .transpose()
+    };
+    let (before, after) = (count("B")?, count("A")?);
+    let mut context = if before.is_some() || after.is_some() {
+        if report || pairs.is_some() {
+            bail!("context cannot be used with a report mode or --pair");
+        }
+        if a.opt_present("dim-context") || a.opt_present("interactive") {
+            bail!("context cannot be used with --dim-context or --interactive");
+        }
+        (!summary_only).then(|| {
+            context::Context::new(before.unwrap_or(0), after.unwrap_or(0))
+        })
+    } else {
+        None
+    };
+    let unparsed = match (a.opt_str("unparsed").as_deref(), a.opt_present("q"))
+    {
+        (None, _) if summary_only => Some(Unparsed::Drop),
+        (None, false) => None,
+        (None, true) | (Some("drop"), _) => Some(Unparsed::Drop),
+        (Some(_), true) => bail!("-q is the same as --unparsed drop"),
+        (Some("pass"), _) => Some(Unparsed::Pass),
+        (Some("mark"), _) => Some(Unparsed::Mark),
+        (Some(other), _) => bail!("unknown --unparsed mode {:?}", other),
+    };
+    let attach = a.opt_present("attach");
+    if attach && context.is_some() {
+        bail!("--attach cannot be used with context");
+    }
+    let mut owner: Option<Owner> = None;
+    let rules = highlight::Rules::new()?;
+    let mut rarity = a
+        .opt_default("rare", "1")
+        .map(|p| rare::Rarity::new(&p))
+        .transpose()?;
+
+    if a.opt_present("interactive") {
+        if report || pairs.is_some() || summary_only {
+            bail!("--interactive cannot be used with a report mode");
+        }
+        if !matches!(format, Format::Short | Format::Long | Format::Bare) {
+            bail!("--interactive is only used with short, long, and bare");
+        }
+    }
+    let ignore_script_errors = a.opt_present("ignore-script-errors");
+    if ignore_script_errors && filter.is_none() {
+        bail!("--ignore-script-errors requires -c");
+    }
+    if a.opt_present("footer")
+        && !matches!(format, Format::Short | Format::Long)
+    {
+        bail!("--footer is only used with the short and long formats");
+    }
+    let topic = kafka_topic(&a)?;
+
+    /*
+     * The watchdog is for a stream of records, not for a session that waits
+     * on the user or for input that is read in full before anything is
+     * shown.
+     */
+    if expect_within.is_some()
+        && ["interactive", "check", "sort"].iter().any(|o| a.opt_present(o))
+    {
+        bail!(
+            "--expect-within cannot be used with --interactive, --check, \
+            or --sort"
+        );
+    }
+
+    /*
+     * With --check, we stop once the options, any scripts, the time range,
+     * and any --where conditions have been found to be valid, before
+     * opening any input, let alone reading it.
+     */
+    if a.opt_present("check") {
+        println!("ok");
+        return Ok(());
+    }
+
+    let mut files = paths
+        .iter()
+        .map(|p| -> Result<Box<dyn Read>> {
+            Ok(match p {
+                p if a.opt_present("reopen") => {
+                    Box::new(input::Reopen::new(p)?)
+                }
+                p if a.opt_present("F") => Box::new(input::Follow::new(p)?),
+                p if remote::is_remote(p) => {
+                    decompress::open(remote::open(p)?)?
+                }
+                p => decompress::open(Box::new(
+                    std::fs::File::open(p)
+                        .map_err(|e| anyhow!("opening file {p:?}: {e}"))?,
+                ))?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let merging = files.len() > 1;
+    let mut child = None;
+    let input: Box<dyn Read> = if merging {
+        /*
+         * Several files are merged below.
+         */
+        Box::new(std::io::empty())
+    } else if let Some(f) = files.pop() {
+        f
+    } else if let Some(cmd) = a.opt_str("cmd") {
+        let mut c = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(&cmd)
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow!("running {cmd:?}: {e}"))?;
+        let stdout = c.stdout.take().unwrap();
+        child = Some(c);
+        Box::new(stdout)
+    } else if let Some((broker, topic)) = &topic {
+        let mut c = kafka(broker, topic)?;
+        let stdout = c.stdout.take().unwrap();
+        child = Some(c);
+        Box::new(stdout)
+    } else {
+        if std::io::stdin().is_terminal() {
+            /*
+             * It is unlikely that the user intended to run the command without
+             * directing a file or pipe as input.
+             */
+            diag::warn("stdin-tty", "reading from stdin, which is a tty");
+        }
+
+        Box::new(std::io::stdin())
+    };
+    let lines: Box<dyn Iterator<Item = Result<input::InputLine>>> =
+        if files.len() > 1 {
+            Box::new(merge::merged(
+                files
+                    .into_iter()
+                    .map(|f| {
+                        Box::new(input::LineReader::new(BufReader::new(f)))
+                            as Box<dyn Iterator<Item = _>>
+                    })
+                    .collect(),
+            )?)
+        } else {
+            Box::new(input::LineReader::new(BufReader::new(input)))
+        };
+    let mut lines: Box<dyn Iterator<Item = Result<input::InputLine>>> =
+        if a.opt_present("sort") { sort::sorted(lines)? } else { lines };
+
+    /*
+     * In an interactive session, the level, filter, and format can be
+     * changed after the input has been read, so the other options only
+     * set where it starts.  Commands come from the terminal if the input
+     * is on stdin.
+     */
+    if a.opt_present("interactive") {
+        let mut session = interactive::Session::load(
+            lines,
+            style,
+            level,
+            |be: &BunyanEntry| {
+                in_range(be.time)
+                    && search.as_ref().map(|s| s.matches(be)).unwrap_or(true)
+            },
+            a.opt_str("c"),
+            &a.opt_strs("where"),
+        )?;
+        let mut commands: Box<dyn BufRead> =
+            if child.is_none() && !a.opt_present("f") {
+                Box::new(BufReader::new(
+                    std::fs::File::open("/dev/tty")
+                        .map_err(|e| anyhow!("opening terminal: {e}"))?,
+                ))
+            } else {
+                Box::new(std::io::stdin().lock())
+            };
+        return session.run(&mut *commands);
+    }
+
+    let mut script_errors: u64 = 0;
+
+    /*
+     * With --strict, every line (other than a blank one) must be a record.
+     */
+    let strict = a.opt_present("strict");
+    let mut invalid: u64 = 0;
+    let mut first_invalid = None;
+    let passthrough = matches!(format, Format::Short | Format::Long)
+        && filter.is_none()
+        && search.is_none()
+        && wheres.is_empty()
+        && !report;
+
+    let footer = a.opt_present("footer") || summary_only;
+
+    let mut out = std::io::stdout().lock();
+    if a.opt_present("legend") && !report && !summary_only {
+        let legend = legend::Legend {
+            format,
+            lookups,
+            line_numbers,
+            offsets,
+            merging,
+            rare: rarity.is_some(),
+            elapsed: show_elapsed,
+            elapsed_only,
+            arrival,
+        };
+        if let Some(l) = legend.render(colour) {
+            writeln!(out, "{}", l)?;
+        }
+    }
+    if matches!(format, Format::Csv | Format::Tsv) && !summary_only {
+        writeln!(out, "{}", delimited::header(format, lookups))?;
+    }
+
+    let watchdog =
+        expect_within.map(|w| start_watchdog(w, a.opt_present("expect-exit")));
+    let mut summary = summary::Summary::new();
+    while let Some(il) = lines.next().transpose()? {
+        let l = &il.text;
+        summary.line();
+
+        /*
+         * If requested, each line of output begins with the position of the
+         * corresponding line in the input, in the style of grep -n and -b,
+         * and, as with grep, that includes the file when there are several.
+         */
+        let mut pos = String::new();
+        if merging && (line_numbers || offsets) {
+            pos += &format!("{}:", paths[il.source]);
+        }
+        if line_numbers {
+            pos += &format!("{}:", il.number);
+        }
+        if offsets {
+            pos += &format!("{}:", il.offset);
+        }
+        if !pos.is_empty() {
+            pos = dim(&pos, colour) + " ";
+        }
+
+        let (mut j, mut be) = match parse_line(l) {
+            Line::Record(j, be) => {
+                summary.record(be.time);
+                owner = Some(Owner::Hidden);
+                (j, be)
+            }
+            other => {
+                summary.unparsed();
+                let why = match other {
+                    Line::UnknownVersion => "unknown bunyan version",
+                    Line::Invalid => "missing required fields",
+                    _ => "not JSON",
+                };
+                if !l.trim().is_empty() {
+                    summary.skipped(why);
+                    if strict {
+                        invalid += 1;
+                        first_invalid.get_or_insert((il.number, why));
+                    }
+                }
+                if diag::enabled(diag::Level::Debug) {
+                    diag::emit(
+                        diag::Level::Debug,
+                        "unparsed",
+                        &format!("line {} is not a record: {}", il.number, why),
+                        &[("line", il.number.into())],
+                    );
+                }
+
+                /*
+                 * Lines that follow a record, such as the rest of a panic
+                 * message, are shown beneath it, and only if it is.
+                 */
+                if attach {
+                    match owner {
+                        Some(Owner::Shown) => {
+                            writeln!(out, "{}    {}", pos, l)?;
+                            continue;
+                        }
+                        Some(Owner::Dimmed) => {
+                            writeln!(out, "{}    {}", pos, dim(l, colour))?;
+                            continue;
+                        }
+                        Some(Owner::Hidden) => continue,
+                        None => (),
+                    }
+                }
+
+                /*
+                 * Unless told otherwise, lines that are not records we
+                 * understand are emitted as-is when we are showing all
+                 * records.  When filtering, they cannot match, so they are
+                 * dropped, or shown as context if requested.
+                 */
+                match unparsed {
+                    Some(Unparsed::Drop) => (),
+                    Some(Unparsed::Pass) => writeln!(out, "{}{}", pos, l)?,
+                    Some(Unparsed::Mark) => {
+                        writeln!(out, "{}{} {}", pos, bold("?", colour), l)?
+                    }
+                    None if context.is_some() => {
+                        let text = format!("{}{}\n", pos, dim(l, colour));
+                        if let Some(c) = &mut context {
+                            c.skipped(&mut out, text.into_bytes())?;
+                        }
+                    }
+                    None if passthrough => writeln!(out, "{}{}", pos, l)?,
+                    None if dim_context => {
+                        writeln!(out, "{}{}", pos, dim(l, colour))?
+                    }
+                    None => (),
+                }
+                continue;
+            }
+        };
+
+        /*
+         * JSON embedded in the message can be examined by filters, though
+         * we do not count this as a change to the record.
+         */
+        if embedded_json {
+            if let (Some((_, v, _)), Some(o)) =
+                (embedded::find(&be.msg), j.as_object_mut())
+            {
+                o.entry("msg_json").or_insert(v);
+            }
+        }
+
+        /*
+         * Spans are timed whether or not their events are shown.
+         */
+        let timed = match &mut span_times {
+            Some(s) => s.observe(&mut be, &mut j),
+            None => false,
+        };
+
+        /*
+         * In the access log modes, records other than those of HTTP requests
+         * are not shown at all.
+         */
+        let request = match format {
+            Format::Access | Format::Clf => match access::Request::new(&be) {
+                Some(r) => Some(r),
+                None => continue,
+            },
+            _ => None,
+        };
+
+        let mut matched = level.map(|level| be.level >= level).unwrap_or(true)
+            && in_range(be.time)
+            && search.as_ref().map(|s| s.matches(&be)).unwrap_or(true)
+            && wheres.iter().all(|w| w.matches(&j));
+        if matched {
+            if let Some(filter) = &mut filter {
+                matched = match filter.matches(&mut j) {
+                    Ok(m) => m,
+                    /*
+                     * If asked, a record on which the script fails is
+                     * treated as not matching, so that one odd record does
+                     * not end the whole run.
+                     */
+                    Err(e) if ignore_script_errors => {
+                        script_errors += 1;
+                        diag::emit(
+                            diag::Level::Warn,
+                            "script-error",
+                            &format!("line {}: {}", il.number, e),
+                            &[("line", il.number.into())],
+                        );
+                        false
+                    }
+                    Err(e) => bail!("line {}: {}", il.number, e),
+                };
+            }
+        }
+
+        if !matched {
+            if !(dim_context || context.is_some()) || report {
+                continue;
+            }
+
+            /*
+             * Show the record without colour, and then dim the whole thing
+             * so that it recedes behind the records that do match.  These
+             * records do not count as displayed for the purposes of -E or
+             * --ditto.
+             */
+            let mut buf = Vec::new();
+            if let Some(r) = &request {
+                match format {
+                    Format::Clf => writeln!(buf, "{}", r.clf())?,
+                    _ => writeln!(buf, "{}", r.access(Colour::None))?,
+                }
+            } else if matches!(format, Format::Json) {
+                writeln!(buf, "{}", l)?;
+            } else if let Some(t) = &template {
+                writeln!(buf, "{}", renderers::fill(t, &be, &mut Vec::new()))?;
+            } else if matches!(format, Format::Csv | Format::Tsv) {
+                emit_delimited(&mut buf, j, &columns, format)?;
+            } else if matches!(format, Format::Bare) {
+                emit_bare(&mut buf, j, &columns)?;
+            } else {
+                if let Some(renderers) = &renderers {
+                    renderers.apply(&mut be);
+                }
+                let plain = Style { colour: Colour::None, ..style };
+                let delta = show_elapsed.then_some(None);
+                let arrived = arrival.then_some(il.arrived);
+                emit_record(&mut buf, &be, &plain, delta, None, arrived)?;
+            }
+            if rarity.is_some() {
+                pos += "  ";
+            }
+            let mut text = pos.into_bytes();
+            for l in String::from_utf8_lossy(&buf).lines() {
+                writeln!(text, "{}", dim(l, colour))?;
+            }
+            match &mut context {
+                Some(c) => c.skipped(&mut out, text)?,
+                None => out.write_all(&text)?,
+            }
+            owner = Some(Owner::Dimmed);
+            continue;
+        }
+
+        if let Some(watchdog) = &watchdog {
+            *watchdog.lock().unwrap() = Instant::now();
+        }
+
+        if let Some(timeline) = &mut timeline {
+            timeline.record(&be);
+            continue;
+        }
+
+        if let Some(compare) = &mut compare {
+            compare.record(&be);
+            continue;
+        }
+
+        if let Some(tasks) = &mut tasks {
+            tasks.record(&be);
+            continue;
+        }
+
+        if let Some(clusters) = &mut clusters {
+            clusters.record(&be);
+            continue;
+        }
+
+        if let Some(rate_report) = &mut rate_report {
+            rate_report.record(&be);
+            continue;
+        }
+
+        if let Some(sagas) = &mut sagas {
+            sagas.record(&be);
+            continue;
+        }
+
+        if let Some(freq) = &mut freq {
+            freq.record(&be);
+            continue;
+        }
+
+        if let Some(n) = &mut matching {
+            *n += 1;
+            continue;
+        }
+
+        if let Some(groups) = &mut groups {
+            groups.record(&be);
+            continue;
+        }
+
+        if let Some(endpoints) = &mut endpoints {
+            endpoints.record(&be);
+            continue;
+        }
+
+        if let Some(requests) = &mut requests {
+            requests.record(&be);
+            continue;
+        }
+
+        if let Some(stats) = &mut stats {
+            stats.record(&be);
+            continue;
+        }
+
+        if slow_spans.is_some() {
+            continue;
+        }
+
+        /*
+         * Whether we have changed the record, and so can no longer show it
+         * exactly as it was read.
+         */
+        let mut edited = timed;
+        if let Some(pairs) = &mut pairs {
+            match pairs.observe(&mut be, &mut j, il.number) {
+                pair::Action::Hold => continue,
+                pair::Action::Paired => edited = true,
+                pair::Action::Show => (),
+            }
+            if unmatched {
+                continue;
+            }
+        }
+
+        summary.displayed(be.time);
+        if summary_only {
+            continue;
+        }
+        owner = Some(Owner::Shown);
+        if let Some(c) = &mut context {
+            c.matched(&mut out, colour)?;
+        }
+
+        if let Some(m) = bursts.as_mut().and_then(|b| b.observe(&be)) {
+            writeln!(out, "{}", bold(&m, colour))?;
+        }
+
+        /*
+         * Rare records are marked, with other records indented to match so
+         * that the columns still line up.
+         */
+        if let Some(rarity) = &mut rarity {
+            pos += &if rarity.observe(&be) {
+                bold("!", colour) + " "
+            } else {
+                "  ".to_string()
+            };
+        }
+
+        /*
+         * A record picked out by a highlight rule is drawn without colour
+         * into a buffer, so that the style of the rule can then be applied
+         * to the whole of each line.
+         */
+        let highlight = match colour {
+            Colour::None => None,
+            _ => rules.style(&be),
+        };
+        let plain = Style { colour: Colour::None, ..style };
+        let mut buf = Vec::new();
+        write!(out, "{}", pos)?;
+        let (o, style): (&mut dyn Write, &Style) = if highlight.is_some() {
+            (&mut buf, &plain)
+        } else {
+            (&mut out, &style)
+        };
+
+        if let Some(r) = &request {
+            match format {
+                Format::Clf => writeln!(o, "{}", r.clf())?,
+                _ => writeln!(o, "{}", r.access(style.colour))?,
+            }
+        } else if matches!(format, Format::Json) {
+            /*
+             * The record is passed on as it was written, rather than as we
+             * would serialise it, unless we have added to it.
+             */
+            if edited {
+                writeln!(o, "{}", j)?;
+            } else {
+                writeln!(o, "{}", l)?;
+            }
+        } else if let Some(t) = &template {
+            writeln!(o, "{}", renderers::fill(t, &be, &mut Vec::new()))?;
+        } else if matches!(format, Format::Csv | Format::Tsv) {
+            emit_delimited(o, j, &columns, format)?;
+        } else if matches!(format, Format::Bare) {
+            emit_bare(o, j, &columns)?;
+        } else {
+            if let Some(renderers) = &renderers {
+                renderers.apply(&mut be);
+            }
+            let delta = show_elapsed.then(|| prev_time.map(|pt| be.time - pt));
+            prev_time = Some(be.time);
+            emit_record(
+                o,
+                &be,
+                style,
+                delta,
+                prev_extra.as_ref(),
+                arrival.then_some(il.arrived),
+            )?;
+            if raw {
+                /*
+                 * Show exactly what the producer wrote, in case the
+                 * formatted output obscures something.
+                 */
+                writeln!(o, "{}", dim(l, style.colour))?;
+            }
+            if ditto {
+                prev_extra = Some(be.extra);
+            }
+        }
+
+        if let Some(h) = highlight {
+            for l in String::from_utf8_lossy(&buf).lines() {
+                writeln!(out, "{}{}\x1b[0m", h, l)?;
+            }
+        }
+    }
+
+    if footer {
+        summary.print(colour);
+    }
+
+    if script_errors > 0 {
+        diag::emit(
+            diag::Level::Warn,
+            "script-errors",
+            &format!(
+                "the script failed on {} record{}, which were not shown",
+                script_errors,
+                if script_errors == 1 { "" } else { "s" }
+            ),
+            &[("count", script_errors.into())],
+        );
+    }
+
+    if let Some(timeline) = &mut timeline {
+        timeline.report(colour);
+    }
+
+    if let Some(compare) = &compare {
+        compare.report(colour);
+    }
+
+    if let Some(tasks) = &tasks {
+        tasks.report(colour);
+    }
+
+    if let Some(clusters) = &clusters {
+        clusters.report(colour);
+    }
+
+    if let Some(rate_report) = &rate_report {
+        rate_report.report(colour);
+    }
+
+    if let Some(sagas) = &sagas {
+        sagas.report(colour);
+    }
+
+    if let Some(freq) = &freq {
+        freq.report(colour);
+    }
+
+    if let Some(n) = matching {
+        println!("{}", n);
+    }
+
+    if let Some(groups) = &groups {
+        groups.report(colour);
+    }
+
+    if let Some(endpoints) = &mut endpoints {
+        endpoints.report(colour);
+    }
+
+    if let Some(requests) = &requests {
+        requests.report();
+    }
+
+    if let Some(stats) = &stats {
+        stats.report(summary.skipped_lines(), colour);
+    }
+
+    if let (Some(n), Some(span_times)) = (slow_spans, &mut span_times) {
+        span_times.report(n, colour);
+    }
+
+    if let Some(pairs) = &pairs {
+        if unmatched {
+            pairs.report_unmatched(colour);
+        } else {
+            pairs.report(colour);
+        }
+    }
+
+    /*
+     * Unless asked to drop them, say how many lines were not records, so
+     * that it is clear whether anything of interest was skipped.
+     */
+    if !strict && stats.is_none() && !matches!(unparsed, Some(Unparsed::Drop)) {
+        summary.report_skipped();
+    }
+
+    if let Some((line, why)) = first_invalid {
+        if invalid == 1 {
+            bail!("line {} is not a record ({})", line, why);
+        }
+        bail!(
+            "{} lines are not records, the first being line {} ({})",
+            invalid,
+            line,
+            why
+        );
+    }
+
+    /*
+     * The command's output is ours, so its exit status should be too.
+     */
+    if let Some(mut c) = child {
+        let st = c.wait()?;
+        if !st.success() {
+            use std::os::unix::process::ExitStatusExt;
+            std::process::exit(
+                st.code().or(st.signal().map(|s| 128 + s)).unwrap_or(1),
+            );
         }
     }
 
@@ -0,0 +1,81 @@
+/*
+ * Interleave the records of several logs by timestamp; e.g., to follow a
+ * request from nexus through sled-agent and into dendrite as a single
+ * timeline.  Each log is assumed to be in order already, as logs written by
+ * one process are, so we need only look at the next line of each.
+ *
+ * As with --sort, lines that are not records stay with the record that
+ * precedes them in their own log.
+ */
+
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use anyhow::Result;
+
+use crate::{input::InputLine, parse_line, Line};
+
+/*
+ * Merge key: the timestamp (seconds and nanoseconds) followed by the index
+ * of the log, so that records with the same time come out in the order in
+ * which the logs were given.
+ */
+type Key = (i64, u32, usize);
+
+struct Source {
+    lines: Box<dyn Iterator<Item = Result<InputLine>>>,
+    /*
+     * The time of the last record we have seen in this log.
+     */
+    last: (i64, u32),
+    head: Option<InputLine>,
+}
+
+pub struct Merge {
+    sources: Vec<Source>,
+    heap: BinaryHeap<Reverse<Key>>,
+}
+
+impl Merge {
+    fn refill(&mut self, i: usize) -> Result<()> {
+        let s = &mut self.sources[i];
+        if let Some(mut l) = s.lines.next().transpose()? {
+            l.source = i;
+            if let Line::Record(_, be) = parse_line(&l.text) {
+                s.last =
+                    (be.time.timestamp(), be.time.timestamp_subsec_nanos());
+            }
+            self.heap.push(Reverse((s.last.0, s.last.1, i)));
+            s.head = Some(l);
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for Merge {
+    type Item = Result<InputLine>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((_, _, i)) = self.heap.pop()?;
+        let l = self.sources[i].head.take()?;
+        if let Err(e) = self.refill(i) {
+            return Some(Err(e));
+        }
+        Some(Ok(l))
+    }
+}
+
+pub fn merged(
+    inputs: Vec<Box<dyn Iterator<Item = Result<InputLine>>>>,
+) -> Result<Merge> {
+    let mut m = Merge {
+        sources: inputs
+            .into_iter()
+            .map(|lines| Source { lines, last: (i64::MIN, 0), head: None })
+            .collect(),
+        heap: BinaryHeap::new(),
+    };
+    for i in 0..m.sources.len() {
+        m.refill(i)?;
+    }
+    Ok(m)
+}
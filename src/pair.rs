@@ -0,0 +1,251 @@
+/*
+ * Pair up records that mark the start and the end of some operation, such
+ * as a request or a background task, by the value of a key field.  When the
+ * end of an operation arrives, it is shown in place of both records as a
+ * single record that includes how long the operation took.  Operations that
+ * never finished are listed at the end of the input.
+ */
+
+use std::collections::{BTreeMap, VecDeque};
+
+use anyhow::{bail, Result};
+use chrono::prelude::*;
+
+use crate::{
+    bold, elapsed, regex::Regex, renderers::field, BunyanEntry, Colour,
+};
+
+/*
+ * One half of a pair: the record that starts an operation, or the record
+ * that finishes one.
+ */
+struct Half {
+    time: DateTime<Utc>,
+    line: u64,
+    key: String,
+    msg: String,
+}
+
+pub enum Action {
+    /*
+     * The record is not part of a pair, and should be displayed as usual.
+     */
+    Show,
+    /*
+     * The record starts an operation, and is held until the operation
+     * finishes.
+     */
+    Hold,
+    /*
+     * The record finishes an operation, and has been replaced with one that
+     * describes the whole operation.
+     */
+    Paired,
+}
+
+pub struct Pairs {
+    start: Regex,
+    done: Regex,
+    key: String,
+    /*
+     * Operations that have started but not finished, in the order in which
+     * they started, for each value of the key.
+     */
+    open: BTreeMap<String, VecDeque<Half>>,
+    /*
+     * Records that finish an operation that we did not see start; e.g.,
+     * because the input begins part way through.
+     */
+    orphans: Vec<Half>,
+    completed: u64,
+    /*
+     * The times of the earliest and latest records we have seen, which
+     * bound the window in which operations could have started and finished.
+     */
+    first: Option<DateTime<Utc>>,
+    last: Option<DateTime<Utc>>,
+}
+
+impl Pairs {
+    /*
+     * The pair is specified as "START=DONE", where each is a regular
+     * expression that matches the message of the corresponding record.
+     */
+    pub fn new(spec: &str, key: String) -> Result<Pairs> {
+        let Some((start, done)) = spec.split_once('=') else {
+            bail!("--pair should be START=DONE, not {:?}", spec);
+        };
+        if start.is_empty() || done.is_empty() {
+            bail!("--pair should be START=DONE, not {:?}", spec);
+        }
+        Ok(Pairs {
+            start: Regex::new(start)?,
+            done: Regex::new(done)?,
+            key,
+            open: Default::default(),
+            orphans: Vec::new(),
+            completed: 0,
+            first: None,
+            last: None,
+        })
+    }
+
+    pub fn observe(
+        &mut self,
+        be: &mut BunyanEntry,
+        j: &mut serde_json::Value,
+        line: u64,
+    ) -> Action {
+        self.first = Some(self.first.map_or(be.time, |t| t.min(be.time)));
+        self.last = self.last.max(Some(be.time));
+        let Some(key) = field(be, &self.key) else {
+            return Action::Show;
+        };
+
+        /*
+         * Check for the end of an operation first, so that a message like
+         * "request done" is not taken as the start of one by a pattern like
+         * "request".
+         */
+        if self.done.is_match(&be.msg) {
+            let Some(start) =
+                self.open.get_mut(&key).and_then(|q| q.pop_front())
+            else {
+                self.orphans.push(Half {
+                    time: be.time,
+                    line,
+                    key,
+                    msg: be.msg.clone(),
+                });
+                return Action::Show;
+            };
+            self.completed += 1;
+            if self.open.get(&key).is_some_and(|q| q.is_empty()) {
+                self.open.remove(&key);
+            }
+
+            let took = be.time - start.time;
+            let msg = format!("{} -> {}", start.msg, be.msg);
+            let extra = [
+                (
+                    "duration_ms".to_string(),
+                    serde_json::Value::from(
+                        took.num_microseconds().unwrap_or(i64::MAX) as f64
+                            / 1000.0,
+                    ),
+                ),
+                (
+                    "started".to_string(),
+                    start
+                        .time
+                        .to_rfc3339_opts(SecondsFormat::Millis, true)
+                        .into(),
+                ),
+            ];
+
+            /*
+             * The record is changed both in its parsed form and in the raw
+             * form used for bare output.
+             */
+            if let Some(o) = j.as_object_mut() {
+                o.insert("msg".into(), msg.clone().into());
+                for (k, v) in extra.iter() {
+                    o.insert(k.clone(), v.clone());
+                }
+            }
+            be.msg = msg;
+            be.extra.extend(extra);
+            return Action::Paired;
+        }
+
+        if self.start.is_match(&be.msg) {
+            self.open.entry(key.clone()).or_default().push_back(Half {
+                time: be.time,
+                line,
+                key,
+                msg: be.msg.clone(),
+            });
+            return Action::Hold;
+        }
+
+        Action::Show
+    }
+
+    fn list(&self, halves: &[&Half], colour: Colour, what: &str) {
+        println!(
+            "{}",
+            bold(
+                &format!(
+                    "{} operation{} {}:",
+                    halves.len(),
+                    if halves.len() == 1 { "" } else { "s" },
+                    what
+                ),
+                colour
+            )
+        );
+        for h in halves {
+            let age = self
+                .last
+                .map(|l| {
+                    elapsed(l - h.time).trim_start_matches('+').to_string()
+                })
+                .unwrap_or_default();
+            println!(
+                "    {} {} = {}: {} (line {}, {} before the end)",
+                h.time.to_rfc3339_opts(SecondsFormat::Millis, true),
+                self.key,
+                h.key,
+                h.msg,
+                h.line,
+                age
+            );
+        }
+    }
+
+    fn unfinished(&self) -> Vec<&Half> {
+        let mut open = self.open.values().flatten().collect::<Vec<_>>();
+        open.sort_by_key(|h| (h.time, h.line));
+        open
+    }
+
+    /*
+     * After the records, list the operations that never finished.
+     */
+    pub fn report(&self, colour: Colour) {
+        let open = self.unfinished();
+        if !open.is_empty() {
+            println!();
+            self.list(&open, colour, "never finished");
+        }
+    }
+
+    /*
+     * In place of the records, report on both the operations that never
+     * finished and those that finished without having started.
+     */
+    pub fn report_unmatched(&self, colour: Colour) {
+        let (Some(first), Some(last)) = (self.first, self.last) else {
+            println!("no records");
+            return;
+        };
+        println!(
+            "from {} to {}: {} operation{} started and finished",
+            first.to_rfc3339_opts(SecondsFormat::Millis, true),
+            last.to_rfc3339_opts(SecondsFormat::Millis, true),
+            self.completed,
+            if self.completed == 1 { "" } else { "s" },
+        );
+
+        let open = self.unfinished();
+        if !open.is_empty() {
+            println!();
+            self.list(&open, colour, "started but never finished");
+        }
+        if !self.orphans.is_empty() {
+            println!();
+            let orphans = self.orphans.iter().collect::<Vec<_>>();
+            self.list(&orphans, colour, "finished without a visible start");
+        }
+    }
+}
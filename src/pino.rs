@@ -0,0 +1,29 @@
+/*
+ * pino writes records that are much like bunyan's, but without the "v"
+ * property (or, in older versions, with "v": 1), and without a "name" unless
+ * one was configured.  The "hostname" and "pid" are also missing if the
+ * logger was created with "base: null".  Its numeric times are taken care
+ * of when the record is read.
+ */
+
+use serde_json::Value;
+
+pub fn to_bunyan(j: &Value) -> Option<Value> {
+    let o = j.as_object()?;
+    match o.get("v") {
+        None => (),
+        Some(v) if v == 1 && o.get("time")?.is_number() => (),
+        Some(_) => return None,
+    }
+    if !o.get("level")?.is_number() || !o.contains_key("time") {
+        return None;
+    }
+
+    let mut r = o.clone();
+    r.insert("v".into(), 0.into());
+    r.entry("name").or_insert_with(|| "-".into());
+    r.entry("hostname").or_insert_with(|| "".into());
+    r.entry("pid").or_insert_with(|| 0.into());
+    r.entry("msg").or_insert_with(|| "".into());
+    Some(Value::Object(r))
+}
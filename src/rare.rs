@@ -0,0 +1,53 @@
+/*
+ * Track how often each message template has been seen, in order to pick out
+ * the unusual record amid the routine ones.  As we process a stream, a record
+ * is rare if its template accounts for only a small share of the records
+ * seen so far.
+ */
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+
+use crate::BunyanEntry;
+
+/*
+ * Until we have seen this many records, we have no real idea what is
+ * routine, so nothing is considered rare.
+ */
+const WARMUP: u64 = 100;
+
+pub struct Rarity {
+    threshold: f64,
+    total: u64,
+    counts: HashMap<(String, String), u64>,
+}
+
+impl Rarity {
+    /*
+     * The threshold is the share of records, as a percentage, below which a
+     * template is rare.
+     */
+    pub fn new(percent: &str) -> Result<Rarity> {
+        let threshold = match percent.trim_end_matches('%').parse::<f64>() {
+            Ok(p) if p > 0.0 && p < 100.0 => p / 100.0,
+            _ => bail!("rarity threshold {:?} should be a percentage", percent),
+        };
+        Ok(Rarity { threshold, total: 0, counts: Default::default() })
+    }
+
+    /*
+     * Count this record, and report whether it is rare.
+     */
+    pub fn observe(&mut self, be: &BunyanEntry) -> bool {
+        let comp = be.component.as_deref().unwrap_or(&be.name).to_string();
+        let tmpl =
+            crate::template::template(be.msg.lines().next().unwrap_or(""));
+
+        let n = self.counts.entry((comp, tmpl)).or_default();
+        *n += 1;
+        self.total += 1;
+
+        self.total > WARMUP && (*n as f64) < self.threshold * self.total as f64
+    }
+}
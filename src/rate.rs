@@ -0,0 +1,252 @@
+/*
+ * Record rates over fixed intervals, and the detection of bursts: intervals
+ * in which many more records were logged than usual.  Rates can be reported
+ * in a table at the end of the input, or bursts can be marked inline as they
+ * happen.  Either way, rates may be considered overall or per component.
+ */
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Result};
+use chrono::prelude::*;
+
+use crate::{bold, BunyanEntry, Colour};
+
+/*
+ * When marking bursts inline, we need a few intervals behind us before we
+ * know what the usual rate is.
+ */
+const MIN_HISTORY: u64 = 3;
+
+pub struct Rates {
+    interval: i64,
+    factor: f64,
+    per_component: bool,
+}
+
+impl Rates {
+    pub fn new(
+        interval: std::time::Duration,
+        factor: Option<&str>,
+        per_component: bool,
+    ) -> Result<Rates> {
+        let interval = interval.as_millis() as i64;
+        if interval == 0 {
+            bail!("rate interval must be at least 1ms");
+        }
+        let factor = match factor.map(|f| f.parse::<f64>()) {
+            None => 3.0,
+            Some(Ok(f)) if f > 1.0 => f,
+            Some(_) => bail!("burst factor should be a number greater than 1"),
+        };
+        Ok(Rates { interval, factor, per_component })
+    }
+
+    fn bucket(&self, t: &DateTime<Utc>) -> i64 {
+        t.timestamp_millis().div_euclid(self.interval)
+    }
+
+    fn start(&self, bucket: i64) -> String {
+        DateTime::from_timestamp_millis(bucket * self.interval)
+            .unwrap_or_default()
+            .to_rfc3339_opts(SecondsFormat::Millis, true)
+    }
+
+    fn key(&self, be: &BunyanEntry) -> String {
+        if self.per_component {
+            be.component.as_deref().unwrap_or(&be.name).to_string()
+        } else {
+            "all components".to_string()
+        }
+    }
+
+    fn describe(&self) -> String {
+        crate::elapsed(chrono::Duration::milliseconds(self.interval))
+            .trim_start_matches('+')
+            .to_string()
+    }
+}
+
+/*
+ * Count records per interval for the whole input, and report at the end.
+ */
+pub struct RateReport {
+    rates: Rates,
+    counts: BTreeMap<String, BTreeMap<i64, u64>>,
+    totals: BTreeMap<i64, u64>,
+}
+
+impl RateReport {
+    pub fn new(rates: Rates) -> RateReport {
+        RateReport {
+            rates,
+            counts: Default::default(),
+            totals: Default::default(),
+        }
+    }
+
+    pub fn record(&mut self, be: &BunyanEntry) {
+        let b = self.rates.bucket(&be.time);
+        *self.totals.entry(b).or_default() += 1;
+        let key = self.rates.key(be);
+        *self.counts.entry(key).or_default().entry(b).or_default() += 1;
+    }
+
+    pub fn report(&self, colour: Colour) {
+        let Some(first) = self.totals.keys().next() else {
+            println!("no records");
+            return;
+        };
+
+        /*
+         * The baseline for each key is the median count over the intervals
+         * in which it logged anything, so that neither a burst nor a long
+         * quiet spell distorts it.
+         */
+        let mut bursts = BTreeMap::new();
+        let mut baselines = BTreeMap::new();
+        for (key, counts) in self.counts.iter() {
+            let mut sorted = counts.values().copied().collect::<Vec<_>>();
+            sorted.sort_unstable();
+            let baseline = sorted[sorted.len() / 2] as f64;
+            baselines.insert(key, baseline);
+            for (b, n) in counts.iter() {
+                if *n as f64 > self.rates.factor * baseline {
+                    bursts.entry(*b).or_insert_with(Vec::new).push((key, *n));
+                }
+            }
+        }
+
+        println!(
+            "{}",
+            bold(
+                &format!(
+                    "{:24} {:>8}  (per {})",
+                    "START",
+                    "RECORDS",
+                    self.rates.describe()
+                ),
+                colour
+            )
+        );
+        /*
+         * Runs of empty intervals are elided, as there may be long gaps in
+         * the input.
+         */
+        let mut next = *first;
+        for (b, n) in self.totals.iter() {
+            match b - next {
+                0 => (),
+                1 => println!("{:24} {:>8}", self.rates.start(next), 0),
+                gap => {
+                    println!("{:>24} {:>8}", format!("({} intervals)", gap), 0)
+                }
+            }
+            let mark = if bursts.contains_key(b) { " !" } else { "" };
+            println!("{:24} {:>8}{}", self.rates.start(*b), n, mark);
+            next = b + 1;
+        }
+
+        println!();
+        if bursts.is_empty() {
+            println!(
+                "no intervals with more than {}x the usual rate",
+                self.rates.factor
+            );
+            return;
+        }
+        println!(
+            "{}",
+            bold(
+                &format!(
+                    "{:24} {:>8} {:>8} {:>7}  BURST",
+                    "START", "RECORDS", "USUAL", "FACTOR"
+                ),
+                colour
+            )
+        );
+        for (b, keys) in bursts.iter() {
+            for (key, n) in keys {
+                let baseline = baselines[key];
+                println!(
+                    "{:24} {:>8} {:>8} {:>6.1}x  {}",
+                    self.rates.start(*b),
+                    n,
+                    baseline,
+                    *n as f64 / baseline,
+                    key
+                );
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Current {
+    bucket: i64,
+    count: u64,
+    flagged: bool,
+    /*
+     * The number of (non-empty) intervals and records before the current
+     * one, from which we determine the usual rate.
+     */
+    intervals: u64,
+    records: u64,
+}
+
+/*
+ * Watch the rate as records arrive, and produce a marker when a burst
+ * begins.
+ */
+pub struct BurstMarkers {
+    rates: Rates,
+    current: BTreeMap<String, Current>,
+}
+
+impl BurstMarkers {
+    pub fn new(rates: Rates) -> BurstMarkers {
+        BurstMarkers { rates, current: Default::default() }
+    }
+
+    pub fn observe(&mut self, be: &BunyanEntry) -> Option<String> {
+        let b = self.rates.bucket(&be.time);
+        let key = self.rates.key(be);
+        let c = self
+            .current
+            .entry(key.clone())
+            .or_insert_with(|| Current { bucket: b, ..Default::default() });
+
+        /*
+         * Records that are out of order are counted in the current
+         * interval, rather than reopening an old one.
+         */
+        if b > c.bucket {
+            c.intervals += 1;
+            c.records += c.count;
+            c.bucket = b;
+            c.count = 0;
+            c.flagged = false;
+        }
+        c.count += 1;
+
+        if c.flagged || c.intervals < MIN_HISTORY {
+            return None;
+        }
+        let usual = c.records as f64 / c.intervals as f64;
+        if (c.count as f64) <= self.rates.factor * usual {
+            return None;
+        }
+
+        c.flagged = true;
+        Some(format!(
+            "*** burst: {} records from {} since {} ({:.1}x the usual \
+            {:.1} per {}) ***",
+            c.count,
+            key,
+            self.rates.start(c.bucket),
+            c.count as f64 / usual,
+            usual,
+            self.rates.describe()
+        ))
+    }
+}
@@ -0,0 +1,69 @@
+/*
+ * The record as seen by a filter script.  Converting the whole record into
+ * rhai values for each line is expensive, and most scripts look at only one
+ * or two fields, so instead we give the script a handle on the parsed record
+ * and convert each field only when the script asks for it.
+ */
+
+use std::rc::Rc;
+
+use rhai::{Array, Dynamic, Engine};
+
+#[derive(Clone)]
+pub struct Record(Rc<serde_json::Value>);
+
+impl Record {
+    pub fn new(j: serde_json::Value) -> Record {
+        Record(Rc::new(j))
+    }
+
+    /*
+     * Recover the record once the script is done with it.  If the script
+     * held on to it (e.g., in a variable), we must make a copy.
+     */
+    pub fn into_inner(self) -> serde_json::Value {
+        Rc::try_unwrap(self.0).unwrap_or_else(|j| (*j).clone())
+    }
+
+    fn get(&self, k: &str) -> Dynamic {
+        self.0.get(k).map(dynamic).unwrap_or(Dynamic::UNIT)
+    }
+}
+
+/*
+ * Convert a JSON value into the equivalent rhai value, as deserialising into
+ * a Dynamic would, but without a detour through serde for the simple cases.
+ */
+fn dynamic(v: &serde_json::Value) -> Dynamic {
+    match v {
+        serde_json::Value::Null => Dynamic::UNIT,
+        serde_json::Value::Bool(b) => (*b).into(),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => i.into(),
+            None => n.as_f64().unwrap_or(f64::NAN).into(),
+        },
+        serde_json::Value::String(s) => s.as_str().into(),
+        other => serde_json::from_value(other.clone()).unwrap_or(Dynamic::UNIT),
+    }
+}
+
+pub fn register(engine: &mut Engine) {
+    engine.register_type_with_name::<Record>("Record");
+    /*
+     * Property access (e.g., "r.msg") falls back on the indexer when there
+     * is no getter for the property, so this covers both forms.  As with a
+     * map, a missing field is ().
+     */
+    engine.register_indexer_get(|r: &mut Record, k: &str| r.get(k));
+    engine.register_fn("contains", |r: &mut Record, k: &str| {
+        r.0.get(k).is_some()
+    });
+    engine.register_fn("keys", |r: &mut Record| -> Array {
+        r.0.as_object()
+            .map(|o| o.keys().map(|k| k.as_str().into()).collect())
+            .unwrap_or_default()
+    });
+    engine.register_fn("to_map", |r: &mut Record| dynamic(&r.0));
+    engine.register_fn("to_string", |r: &mut Record| r.0.to_string());
+    engine.register_fn("to_debug", |r: &mut Record| r.0.to_string());
+}
@@ -0,0 +1,671 @@
+/*
+ * A small regular expression matcher, sufficient for matching patterns
+ * against log messages and field values.  It supports literals, ".",
+ * character classes (including ranges, negation, and the \d, \w, and \s
+ * shorthands), the anchors "^", "$", and "\b", grouping, alternation, and the
+ * greedy quantifiers "*", "+", "?", and "{m,n}".  A leading "(?i)" makes the
+ * match case-insensitive.
+ *
+ * Since the patterns come from the user, and one slow match would hold up
+ * everything else, a pattern is compiled into a program whose every way of
+ * matching is followed at once, a character at a time (as described by
+ * Thompson and Pike), rather than by backtracking.  A search thus takes time
+ * proportional to the length of the text and the size of the program, for
+ * any pattern, and the size of the program is limited when it is compiled.
+ */
+
+use anyhow::{bail, Result};
+
+/*
+ * The number of instructions a pattern may compile to; counted repetition,
+ * as in "(a|b){1000}", repeats the instructions of what is repeated.
+ */
+const MAX_INSTS: usize = 10_000;
+
+#[derive(Debug, Clone)]
+enum ClassItem {
+    Range(char, char),
+    Digit(bool),
+    Word(bool),
+    Space(bool),
+}
+
+impl ClassItem {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            ClassItem::Range(a, b) => *a <= c && c <= *b,
+            ClassItem::Digit(neg) => c.is_ascii_digit() != *neg,
+            ClassItem::Word(neg) => is_word(c) != *neg,
+            ClassItem::Space(neg) => c.is_whitespace() != *neg,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    Any,
+    Class(Vec<ClassItem>, bool),
+    Start,
+    End,
+    WordBoundary(bool),
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat(Box<Node>, usize, Option<usize>),
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    pattern: &'a str,
+}
+
+impl Parser<'_> {
+    fn alt(&mut self) -> Result<Node> {
+        let mut branches = vec![self.concat()?];
+        while self.chars.peek() == Some(&'|') {
+            self.chars.next();
+            branches.push(self.concat()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.pop().unwrap()
+        } else {
+            Node::Alt(branches)
+        })
+    }
+
+    fn concat(&mut self) -> Result<Node> {
+        let mut nodes = Vec::new();
+        while let Some(&c) = self.chars.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            nodes.push(self.quantifier(atom)?);
+        }
+        Ok(Node::Concat(nodes))
+    }
+
+    fn quantifier(&mut self, atom: Node) -> Result<Node> {
+        let (min, max) = match self.chars.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                self.chars.next();
+                let mut spec = String::new();
+                loop {
+                    match self.chars.next() {
+                        Some('}') => break,
+                        Some(c) => spec.push(c),
+                        None => bail!("unterminated {{ in {:?}", self.pattern),
+                    }
+                }
+                let bad = || anyhow::anyhow!("bad repetition in {:?}", spec);
+                let (min, max) = match spec.split_once(',') {
+                    Some((a, "")) => (a.parse().map_err(|_| bad())?, None),
+                    Some((a, b)) => (
+                        a.parse().map_err(|_| bad())?,
+                        Some(b.parse().map_err(|_| bad())?),
+                    ),
+                    None => {
+                        let n = spec.parse().map_err(|_| bad())?;
+                        (n, Some(n))
+                    }
+                };
+                return Ok(Node::Repeat(Box::new(atom), min, max));
+            }
+            _ => return Ok(atom),
+        };
+        self.chars.next();
+        Ok(Node::Repeat(Box::new(atom), min, max))
+    }
+
+    fn escape(&mut self) -> Result<Node> {
+        let Some(c) = self.chars.next() else {
+            bail!("trailing backslash in {:?}", self.pattern);
+        };
+        Ok(match c {
+            'd' | 'D' | 'w' | 'W' | 's' | 'S' => {
+                Node::Class(vec![self.shorthand(c)], false)
+            }
+            'b' => Node::WordBoundary(true),
+            'B' => Node::WordBoundary(false),
+            'n' => Node::Char('\n'),
+            't' => Node::Char('\t'),
+            other => Node::Char(other),
+        })
+    }
+
+    fn shorthand(&self, c: char) -> ClassItem {
+        match c {
+            'd' => ClassItem::Digit(false),
+            'D' => ClassItem::Digit(true),
+            'w' => ClassItem::Word(false),
+            'W' => ClassItem::Word(true),
+            's' => ClassItem::Space(false),
+            _ => ClassItem::Space(true),
+        }
+    }
+
+    fn class(&mut self) -> Result<Node> {
+        let mut items = Vec::new();
+        let negated = self.chars.peek() == Some(&'^');
+        if negated {
+            self.chars.next();
+        }
+
+        let mut first = true;
+        loop {
+            let c = match self.chars.next() {
+                None => bail!("unterminated [ in {:?}", self.pattern),
+                Some(']') if !first => break,
+                Some('\\') => match self.chars.next() {
+                    Some(s @ ('d' | 'D' | 'w' | 'W' | 's' | 'S')) => {
+                        items.push(self.shorthand(s));
+                        first = false;
+                        continue;
+                    }
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some(c) => c,
+                    None => bail!("trailing backslash in {:?}", self.pattern),
+                },
+                Some(c) => c,
+            };
+            first = false;
+
+            /*
+             * A "-" at the end of the class is a literal.
+             */
+            let mut ahead = self.chars.clone();
+            if ahead.next() == Some('-') && !matches!(ahead.next(), Some(']')) {
+                self.chars.next();
+                match self.chars.next() {
+                    Some(end) if end >= c => {
+                        items.push(ClassItem::Range(c, end))
+                    }
+                    _ => bail!("bad range in {:?}", self.pattern),
+                }
+            } else {
+                items.push(ClassItem::Range(c, c));
+            }
+        }
+        Ok(Node::Class(items, negated))
+    }
+
+    fn atom(&mut self) -> Result<Node> {
+        Ok(match self.chars.next().unwrap() {
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '\\' => self.escape()?,
+            '[' => self.class()?,
+            '(' => {
+                /*
+                 * All groups are non-capturing, so accept that syntax too.
+                 */
+                let mut ahead = self.chars.clone();
+                if ahead.next() == Some('?') && ahead.next() == Some(':') {
+                    self.chars.next();
+                    self.chars.next();
+                }
+                let inner = self.alt()?;
+                if self.chars.next() != Some(')') {
+                    bail!("unbalanced ( in {:?}", self.pattern);
+                }
+                inner
+            }
+            c @ ('*' | '+' | '?' | '{') => {
+                bail!("nothing to repeat before {:?} in {:?}", c, self.pattern)
+            }
+            c => Node::Char(c),
+        })
+    }
+}
+
+/*
+ * An instruction of the compiled pattern.  Split tries both of its targets,
+ * preferring the first, which is how the quantifiers are made greedy.
+ */
+#[derive(Debug, Clone)]
+enum Inst {
+    One(Node),
+    Start,
+    End,
+    WordBoundary(bool),
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+#[derive(Debug, Clone)]
+pub struct Regex {
+    prog: Vec<Inst>,
+    icase: bool,
+}
+
+struct Compiler<'a> {
+    prog: Vec<Inst>,
+    pattern: &'a str,
+}
+
+impl Compiler<'_> {
+    fn emit(&mut self, i: Inst) -> Result<usize> {
+        if self.prog.len() >= MAX_INSTS {
+            bail!("pattern {:?} is too large", self.pattern);
+        }
+        self.prog.push(i);
+        Ok(self.prog.len() - 1)
+    }
+
+    /*
+     * Point the Split or Jump at "at" to "to" (the second target, for a
+     * Split), once "to" is known.
+     */
+    fn patch(&mut self, at: usize, to: usize) {
+        match &mut self.prog[at] {
+            Inst::Split(_, t) | Inst::Jump(t) => *t = to,
+            _ => unreachable!(),
+        }
+    }
+
+    fn node(&mut self, n: &Node) -> Result<()> {
+        match n {
+            Node::Char(_) | Node::Any | Node::Class(..) => {
+                self.emit(Inst::One(n.clone()))?;
+            }
+            Node::Start => {
+                self.emit(Inst::Start)?;
+            }
+            Node::End => {
+                self.emit(Inst::End)?;
+            }
+            Node::WordBoundary(want) => {
+                self.emit(Inst::WordBoundary(*want))?;
+            }
+            Node::Concat(nodes) => {
+                for n in nodes {
+                    self.node(n)?;
+                }
+            }
+            Node::Alt(branches) => {
+                let mut jumps = Vec::new();
+                for (i, b) in branches.iter().enumerate() {
+                    if i + 1 == branches.len() {
+                        self.node(b)?;
+                        break;
+                    }
+                    let split = self.emit(Inst::Split(0, 0))?;
+                    self.prog[split] = Inst::Split(split + 1, 0);
+                    self.node(b)?;
+                    jumps.push(self.emit(Inst::Jump(0))?);
+                    let next = self.prog.len();
+                    self.patch(split, next);
+                }
+                let end = self.prog.len();
+                for j in jumps {
+                    self.patch(j, end);
+                }
+            }
+            Node::Repeat(inner, min, max) => {
+                for _ in 0..*min {
+                    self.node(inner)?;
+                }
+                match max {
+                    None => {
+                        let split = self.emit(Inst::Split(0, 0))?;
+                        self.prog[split] = Inst::Split(split + 1, 0);
+                        self.node(inner)?;
+                        self.emit(Inst::Jump(split))?;
+                        let end = self.prog.len();
+                        self.patch(split, end);
+                    }
+                    Some(max) => {
+                        let mut splits = Vec::new();
+                        for _ in *min..*max {
+                            let split = self.emit(Inst::Split(0, 0))?;
+                            self.prog[split] = Inst::Split(split + 1, 0);
+                            splits.push(split);
+                            self.node(inner)?;
+                        }
+                        let end = self.prog.len();
+                        for s in splits {
+                            self.patch(s, end);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/*
+ * A way of matching that is still in progress: the instruction it has
+ * reached, and where in the text its match began.
+ */
+#[derive(Clone, Copy)]
+struct Thread {
+    pc: usize,
+    start: usize,
+}
+
+/*
+ * The threads at one position in the text, in order of preference.  So that
+ * each instruction is followed only once at each position, "seen" holds the
+ * position (plus one) at which it was last reached.
+ */
+struct Threads {
+    list: Vec<Thread>,
+    seen: Vec<usize>,
+}
+
+impl Threads {
+    fn new(n: usize) -> Threads {
+        Threads { list: Vec::new(), seen: vec![0; n] }
+    }
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Regex> {
+        let (icase, rest) = match pattern.strip_prefix("(?i)") {
+            Some(rest) => (true, rest),
+            None => (false, pattern),
+        };
+        let mut p = Parser { chars: rest.chars().peekable(), pattern };
+        let root = p.alt()?;
+        if p.chars.next().is_some() {
+            bail!("unbalanced ) in {:?}", pattern);
+        }
+
+        let mut c = Compiler { prog: Vec::new(), pattern };
+        c.node(&root)?;
+        c.emit(Inst::Match)?;
+        Ok(Regex { prog: c.prog, icase })
+    }
+
+    fn eq(&self, a: char, b: char) -> bool {
+        a == b
+            || (self.icase
+                && a.to_lowercase().next() == b.to_lowercase().next())
+    }
+
+    /*
+     * Whether a node that matches exactly one character matches this one.
+     */
+    fn single(&self, n: &Node, c: char) -> bool {
+        match n {
+            Node::Char(x) => self.eq(*x, c),
+            Node::Any => c != '\n',
+            Node::Class(items, neg) => {
+                let hit = items.iter().any(|i| i.matches(c))
+                    || (self.icase
+                        && c.is_alphabetic()
+                        && items.iter().any(|i| {
+                            c.to_lowercase()
+                                .chain(c.to_uppercase())
+                                .any(|c| i.matches(c))
+                        }));
+                hit != *neg
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /*
+     * Add a thread at position "i" of "s", following any instructions that
+     * do not consume a character, and in order of preference.  The stack
+     * takes the place of recursion, which a long chain of Splits (as from
+     * "a{0,1000}") could otherwise take too deep.
+     */
+    fn add(&self, ts: &mut Threads, s: &[char], i: usize, t: Thread) {
+        let mut stack = vec![t.pc];
+        while let Some(pc) = stack.pop() {
+            if ts.seen[pc] == i + 1 {
+                continue;
+            }
+            ts.seen[pc] = i + 1;
+            let next = match &self.prog[pc] {
+                Inst::One(_) | Inst::Match => {
+                    ts.list.push(Thread { pc, start: t.start });
+                    continue;
+                }
+                Inst::Start => (i == 0).then_some(pc + 1),
+                Inst::End => (i == s.len()).then_some(pc + 1),
+                Inst::WordBoundary(want) => {
+                    let before = i > 0 && is_word(s[i - 1]);
+                    let after = i < s.len() && is_word(s[i]);
+                    ((before != after) == *want).then_some(pc + 1)
+                }
+                Inst::Jump(to) => Some(*to),
+                Inst::Split(a, b) => {
+                    stack.push(*b);
+                    Some(*a)
+                }
+            };
+            if let Some(next) = next {
+                stack.push(next);
+            }
+        }
+    }
+
+    /*
+     * The leftmost match in "s" that begins at or after "from", as the
+     * start and end positions.  Of the matches that begin there, the one
+     * preferred by the greedy quantifiers is chosen, unless "any" is set, in
+     * which case the first match found will do.
+     */
+    fn search(
+        &self,
+        s: &[char],
+        from: usize,
+        any: bool,
+    ) -> Option<(usize, usize)> {
+        let n = self.prog.len();
+        let (mut cur, mut next) = (Threads::new(n), Threads::new(n));
+        let mut found = None;
+        for i in from..=s.len() {
+            if found.is_none() {
+                self.add(&mut cur, s, i, Thread { pc: 0, start: i });
+            }
+            if cur.list.is_empty() && found.is_some() {
+                break;
+            }
+            for t in std::mem::take(&mut cur.list) {
+                match &self.prog[t.pc] {
+                    Inst::One(n) => {
+                        if i < s.len() && self.single(n, s[i]) {
+                            let t = Thread { pc: t.pc + 1, ..t };
+                            self.add(&mut next, s, i + 1, t);
+                        }
+                    }
+                    Inst::Match => {
+                        /*
+                         * Threads after this one are less preferred, so
+                         * they are dropped.
+                         */
+                        found = Some((t.start, i));
+                        if any {
+                            return found;
+                        }
+                        break;
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            std::mem::swap(&mut cur, &mut next);
+        }
+        found
+    }
+
+    pub fn is_match(&self, s: &str) -> bool {
+        let chars = s.chars().collect::<Vec<_>>();
+        self.search(&chars, 0, true).is_some()
+    }
+
+    /*
+     * The byte ranges of the successive non-overlapping matches in "s",
+     * leftmost first.  Empty matches are of no use to anyone and are left
+     * out.
+     */
+    pub fn find_all(&self, s: &str) -> Vec<(usize, usize)> {
+        let chars = s.chars().collect::<Vec<_>>();
+        let mut offsets = s.char_indices().map(|(b, _)| b).collect::<Vec<_>>();
+        offsets.push(s.len());
+
+        let mut found = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            match self.search(&chars, i, false) {
+                Some((a, b)) if b > a => {
+                    found.push((offsets[a], offsets[b]));
+                    i = b;
+                }
+                Some((a, _)) => i = a + 1,
+                None => break,
+            }
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::Regex;
+
+    fn is_match(pattern: &str, s: &str) -> bool {
+        Regex::new(pattern).unwrap().is_match(s)
+    }
+
+    #[test]
+    fn parse_errors() {
+        for p in ["(a", "a)", "[a", "a{2", "a{x}", "*a", "a\\", "[z-a]"] {
+            assert!(Regex::new(p).is_err(), "{:?} should not parse", p);
+        }
+        for p in ["", "a|", "(?:ab)+", "[-a]", "[a-]", "[]a]", "a{2,}"] {
+            assert!(Regex::new(p).is_ok(), "{:?} should parse", p);
+        }
+    }
+
+    #[test]
+    fn literals_and_any() {
+        assert!(is_match("abc", "xxabcxx"));
+        assert!(!is_match("abd", "xxabcxx"));
+        assert!(is_match("a.c", "abc"));
+        assert!(!is_match("a.c", "a\nc"));
+        assert!(is_match("a\\.c", "a.c"));
+        assert!(!is_match("a\\.c", "abc"));
+    }
+
+    #[test]
+    fn anchors() {
+        assert!(is_match("^abc", "abcdef"));
+        assert!(!is_match("^abc", "xabc"));
+        assert!(is_match("def$", "abcdef"));
+        assert!(!is_match("def$", "defx"));
+        assert!(is_match("^$", ""));
+        assert!(is_match("\\bcat\\b", "a cat sat"));
+        assert!(!is_match("\\bcat\\b", "concatenate"));
+        assert!(is_match("\\Bcat\\B", "concatenate"));
+    }
+
+    #[test]
+    fn classes() {
+        assert!(is_match("^[a-c]+$", "abcabc"));
+        assert!(!is_match("^[a-c]+$", "abcd"));
+        assert!(is_match("^[^0-9]+$", "abc"));
+        assert!(!is_match("^[^0-9]+$", "ab1"));
+        assert!(is_match("^\\d{3}-\\d{4}$", "555-1234"));
+        assert!(is_match("^\\w+\\s\\w+$", "hello world"));
+        assert!(!is_match("\\S", " \t"));
+        assert!(is_match("^[a-]+$", "a-a"));
+        assert!(is_match("^[\\d.]+$", "1.25"));
+    }
+
+    #[test]
+    fn alternation_and_groups() {
+        assert!(is_match("^(cat|dog)s?$", "dogs"));
+        assert!(is_match("^(cat|dog)s?$", "cat"));
+        assert!(!is_match("^(cat|dog)s?$", "cow"));
+        assert!(is_match("^(?:ab)+$", "ababab"));
+        assert!(!is_match("^(?:ab)+$", "ababa"));
+        assert!(is_match("^a(b|)c$", "ac"));
+    }
+
+    #[test]
+    fn quantifiers() {
+        assert!(is_match("^a*$", ""));
+        assert!(is_match("^a+$", "aaa"));
+        assert!(!is_match("^a+$", ""));
+        assert!(is_match("^ab?c$", "ac"));
+        assert!(is_match("^a{2,3}$", "aaa"));
+        assert!(!is_match("^a{2,3}$", "aaaa"));
+        assert!(!is_match("^a{2,3}$", "a"));
+        assert!(is_match("^a{2}$", "aa"));
+        assert!(is_match("^a{2,}$", "aaaaa"));
+        assert!(is_match("^(a|b)*c$", "ababc"));
+    }
+
+    #[test]
+    fn case_insensitive() {
+        assert!(is_match("(?i)error", "ERROR: disk"));
+        assert!(is_match("(?i)^[a-c]+$", "ABC"));
+        assert!(!is_match("error", "ERROR"));
+    }
+
+    #[test]
+    fn find_all() {
+        let re = Regex::new("a+").unwrap();
+        assert_eq!(re.find_all("baaca"), vec![(1, 3), (4, 5)]);
+        let re = Regex::new("é+").unwrap();
+        assert_eq!(re.find_all("xéé"), vec![(1, 5)]);
+        let re = Regex::new("x*").unwrap();
+        assert!(re.find_all("abc").is_empty());
+        let re = Regex::new("a|ab").unwrap();
+        assert_eq!(re.find_all("xab"), vec![(1, 2)]);
+        let re = Regex::new("ab|a").unwrap();
+        assert_eq!(re.find_all("xab"), vec![(1, 3)]);
+        let re = Regex::new("\\d+").unwrap();
+        assert_eq!(re.find_all("id 12 of 345"), vec![(3, 5), (9, 12)]);
+    }
+
+    /*
+     * A nested repetition that cannot match would take a backtracking
+     * matcher exponential time to fail.
+     */
+    #[test]
+    fn pathological() {
+        let s = "a".repeat(30);
+        let start = Instant::now();
+        assert!(!is_match("(a*)*c", &s));
+        assert!(is_match("(a*)*c", &format!("{}c", s)));
+        assert!(!is_match("^(a|aa)+$", &format!("{}b", s)));
+        assert!(Regex::new("(a*)*c").unwrap().find_all(&s).is_empty());
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    /*
+     * Repeating a group many times must neither overflow the stack nor
+     * give up.
+     */
+    #[test]
+    fn deep() {
+        let s = "ab".repeat(100_000);
+        assert!(is_match("^(ab)+$", &s));
+        assert!(!is_match("(ab)+c", &s));
+        assert!(is_match("^(ab)+c$", &format!("{}c", s)));
+        assert!(is_match("^a{0,1000}$", &"a".repeat(1000)));
+    }
+
+    #[test]
+    fn too_large() {
+        assert!(Regex::new("(a|b){100000}").is_err());
+        assert!(Regex::new("\\d{1,2}").is_ok());
+    }
+}
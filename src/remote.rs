@@ -0,0 +1,169 @@
+/*
+ * Reading logs from object storage or a web server, given as an s3:// or
+ * http(s):// URL in place of a file name.  The download is streamed, so
+ * records appear as they arrive.  The work is done by the usual tools (the
+ * AWS CLI and curl), which already know about credentials, proxies, and the
+ * like.
+ */
+
+use std::{
+    io::Read,
+    process::{Child, ChildStdout, Command, Stdio},
+    thread::JoinHandle,
+};
+
+use anyhow::{anyhow, Result};
+
+/*
+ * If an HTTP download is interrupted, we pick up where it left off, but not
+ * indefinitely.
+ */
+const MAX_RESUMES: u32 = 5;
+
+pub fn is_remote(path: &str) -> bool {
+    ["s3://", "https://", "http://"].iter().any(|p| path.starts_with(p))
+}
+
+/*
+ * The output of a command.  At the end of the output we make sure that the
+ * command succeeded, so that (e.g.) a failed download is not mistaken for a
+ * short log.
+ */
+pub struct Spawned {
+    name: String,
+    child: Child,
+    out: ChildStdout,
+    /*
+     * A thread that feeds the command its input, if it needs any.
+     */
+    feeder: Option<JoinHandle<std::io::Result<u64>>>,
+}
+
+impl Spawned {
+    pub fn new(
+        mut cmd: Command,
+        input: Option<Box<dyn Read + Send>>,
+    ) -> Result<Spawned> {
+        let name = cmd.get_program().to_string_lossy().to_string();
+        if input.is_some() {
+            cmd.stdin(Stdio::piped());
+        }
+        let mut child = cmd
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow!("running {name}: {e}"))?;
+        let out = child.stdout.take().unwrap();
+        let feeder = input.map(|mut input| {
+            let mut stdin = child.stdin.take().unwrap();
+            std::thread::spawn(move || std::io::copy(&mut input, &mut stdin))
+        });
+        Ok(Spawned { name, child, out, feeder })
+    }
+}
+
+impl Read for Spawned {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.out.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            if let Some(f) = self.feeder.take() {
+                f.join().map_err(|_| {
+                    std::io::Error::other("input thread panicked")
+                })??;
+            }
+            let st = self.child.wait()?;
+            if !st.success() {
+                return Err(std::io::Error::other(format!(
+                    "{} failed ({st})",
+                    self.name
+                )));
+            }
+        }
+        Ok(n)
+    }
+}
+
+/*
+ * An HTTP download with curl.  If the connection is lost part way through,
+ * we ask for the rest of the body with a range request.  Ranges refer to
+ * the body as stored, so while the first request may allow the server to
+ * compress the body in transit, requests to resume do not.
+ */
+struct Http {
+    url: String,
+    got: u64,
+    resumes: u32,
+    curl: Spawned,
+}
+
+fn curl(url: &str, from: u64) -> Result<Spawned> {
+    let mut c = Command::new("curl");
+    c.args(["--fail", "--silent", "--show-error", "--location"]);
+    if from == 0 {
+        c.arg("--compressed");
+    } else {
+        /*
+         * curl checks that the server honours the range, and fails if it
+         * would send the whole body again.
+         */
+        c.args(["--continue-at", &from.to_string()]);
+    }
+    c.arg("--").arg(url);
+    Spawned::new(c, None)
+}
+
+impl Read for Http {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            match self.curl.read(buf) {
+                Ok(n) => {
+                    self.got += n as u64;
+                    return Ok(n);
+                }
+                Err(e) if self.resumes < MAX_RESUMES => {
+                    let interrupted = self
+                        .curl
+                        .child
+                        .try_wait()
+                        .ok()
+                        .flatten()
+                        .and_then(|st| st.code())
+                        /*
+                         * Partial file, timeout, empty reply, send and
+                         * receive failures, and HTTP/2 stream errors.
+                         */
+                        .is_some_and(|c| [18, 28, 52, 55, 56, 92].contains(&c));
+                    if !interrupted {
+                        return Err(e);
+                    }
+                    self.resumes += 1;
+                    crate::diag::warn(
+                        "resume",
+                        &format!(
+                            "download of {} interrupted after {} bytes ({}); \
+                            resuming",
+                            self.url, self.got, e
+                        ),
+                    );
+                    self.curl = curl(&self.url, self.got)
+                        .map_err(|e| std::io::Error::other(e.to_string()))?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+pub fn open(url: &str) -> Result<Box<dyn Read + Send>> {
+    Ok(if url.starts_with("s3://") {
+        let mut c = Command::new("aws");
+        c.args(["s3", "cp", "--quiet", url, "-"]);
+        Box::new(Spawned::new(c, None)?)
+    } else {
+        Box::new(Http {
+            url: url.to_string(),
+            got: 0,
+            resumes: 0,
+            curl: curl(url, 0)?,
+        })
+    })
+}
@@ -0,0 +1,268 @@
+/*
+ * Some components produce records whose interesting content is spread across
+ * a number of structured fields; e.g., dropshot request logs or steno saga
+ * events.  A renderer recognises such a record and condenses it into a
+ * single purpose-built line, which replaces the message in the output.  The
+ * fields consumed by the renderer are then omitted from the field list.
+ *
+ * There are built-in renderers for well-known components, and more may be
+ * defined in the configuration file.
+ */
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::BunyanEntry;
+
+pub struct Rendered {
+    pub line: String,
+    pub used: Vec<String>,
+}
+
+/*
+ * A renderer defined in the configuration file.  The template refers to
+ * fields of the record in braces; e.g., "{method} {uri} -> {status}".
+ */
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateRenderer {
+    /*
+     * The renderer applies to records from this component (or, if the
+     * record has no component, with this name).
+     */
+    pub component: String,
+    /*
+     * If specified, the renderer only applies to records with this message.
+     */
+    pub msg: Option<String>,
+    pub template: String,
+}
+
+/*
+ * Produce the value of a field, whether it is one of the standard bunyan
+ * fields or an extra field.
+ */
+pub fn field(be: &BunyanEntry, name: &str) -> Option<String> {
+    Some(match name {
+        "msg" => be.msg.clone(),
+        "name" => be.name.clone(),
+        "hostname" => be.hostname.clone(),
+        "pid" => be.pid.to_string(),
+        "level" => be.level.render().trim_end().to_string(),
+        "time" => be.time.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        "component" => be.component.clone()?,
+        other => match lookup(|k| be.extra.get(k), other)? {
+            Value::String(s) => s.clone(),
+            v => v.to_string(),
+        },
+    })
+}
+
+/*
+ * Find a field, or a value nested within one by a path such as
+ * "req.method" or "spans.0.name".  A field whose name contains a dot is
+ * found as it is.
+ */
+pub fn lookup<'a>(
+    get: impl Fn(&str) -> Option<&'a Value>,
+    path: &str,
+) -> Option<&'a Value> {
+    if let Some(v) = get(path) {
+        return Some(v);
+    }
+
+    let mut parts = path.split('.');
+    let mut v = get(parts.next()?)?;
+    for p in parts {
+        v = match v {
+            Value::Object(o) => o.get(p)?,
+            Value::Array(a) => a.get(p.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(v)
+}
+
+/*
+ * Substitute "{field}" references in a template.  Fields that are not
+ * present in the record are rendered as "-".  A literal brace may be written
+ * as "{{" or "}}".
+ */
+pub fn fill(
+    template: &str,
+    be: &BunyanEntry,
+    used: &mut Vec<String>,
+) -> String {
+    let mut out = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let name = chars
+                    .by_ref()
+                    .take_while(|c| *c != '}')
+                    .collect::<String>();
+                out.push_str(&field(be, &name).unwrap_or_else(|| "-".into()));
+                used.push(name);
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+impl TemplateRenderer {
+    fn render(&self, be: &BunyanEntry) -> Option<Rendered> {
+        if be.component.as_deref().unwrap_or(&be.name) != self.component {
+            return None;
+        }
+        if self.msg.as_ref().map(|m| m != &be.msg).unwrap_or(false) {
+            return None;
+        }
+
+        let mut used = Vec::new();
+        let line = fill(&self.template, be, &mut used);
+        Some(Rendered { line, used })
+    }
+}
+
+pub fn latency(us: &Value) -> Option<String> {
+    let us = us.as_f64()?;
+    Some(if us >= 1_000_000.0 {
+        format!("{:.3}s", us / 1_000_000.0)
+    } else {
+        format!("{:.3}ms", us / 1000.0)
+    })
+}
+
+/*
+ * Dropshot logs "incoming request" and "request completed" records with the
+ * method, URI, and (for completion) the status and latency.  Other servers
+ * log the request and response as objects instead, as node's bunyan does
+ * with its standard "req" and "res" serialisers, so we accept those too.
+ */
+fn dropshot(be: &BunyanEntry) -> Option<Rendered> {
+    let get = |k: &str| be.extra.get(k);
+    let text = |v: &Value| {
+        v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string())
+    };
+    let mut used = Vec::new();
+
+    let req = get("req").and_then(Value::as_object);
+    let (method, uri) = match (get("method"), get("uri")) {
+        (Some(m), Some(u)) => {
+            used.extend(["method".to_string(), "uri".to_string()]);
+            (m.as_str()?, u.as_str()?)
+        }
+        _ => {
+            let req = req?;
+            used.push("req".to_string());
+            let uri = req.get("uri").or_else(|| req.get("url"))?;
+            (req.get("method")?.as_str()?, uri.as_str()?)
+        }
+    };
+
+    let mut line = format!("{}: {} {}", be.msg, method, uri);
+    let res = ["res", "response"]
+        .into_iter()
+        .find_map(|k| Some((k, get(k)?.as_object()?)));
+    let code = match (get("response_code"), res) {
+        (Some(code), _) => Some(("response_code", code)),
+        (None, Some((k, res))) => ["status_code", "statusCode", "status"]
+            .into_iter()
+            .find_map(|s| res.get(s))
+            .map(|code| (k, code)),
+        (None, None) => None,
+    };
+    if let Some((k, code)) = code {
+        line += &format!(" -> {}", text(code));
+        used.push(k.into());
+    }
+    if let Some(l) = get("latency_us").and_then(latency) {
+        line += &format!(" in {}", l);
+        used.push("latency_us".into());
+    }
+    let remote = match get("remote_addr") {
+        Some(r) => Some(("remote_addr", r)),
+        None => req.and_then(|r| r.get("remoteAddress")).map(|r| ("req", r)),
+    };
+    if let Some((k, r)) = remote.and_then(|(k, r)| Some((k, r.as_str()?))) {
+        line += &format!(" from {}", r);
+        used.push(k.into());
+    }
+
+    Some(Rendered { line, used })
+}
+
+/*
+ * Steno saga execution records identify the saga and the node within it.
+ */
+fn steno(be: &BunyanEntry) -> Option<Rendered> {
+    let id = be.extra.get("saga_id")?.as_str()?;
+    let mut used = vec!["saga_id".to_string()];
+
+    /*
+     * Saga IDs are UUIDs; the first group is generally enough to tell them
+     * apart in a single log.
+     */
+    let mut line = "saga ".to_string();
+    if let Some(name) = be.extra.get("saga_name").and_then(|n| n.as_str()) {
+        line += &format!("{} ", name);
+        used.push("saga_name".into());
+    }
+    line += &format!("[{}]", id.split('-').next().unwrap_or(id));
+    if let Some(node) = be.extra.get("node_id") {
+        line += &format!(" node {}", node);
+        used.push("node_id".into());
+    }
+    line += &format!(": {}", be.msg);
+    if let Some(ev) = be.extra.get("event_type").and_then(|e| e.as_str()) {
+        line += &format!(" ({})", ev);
+        used.push("event_type".into());
+    }
+
+    Some(Rendered { line, used })
+}
+
+pub struct Registry {
+    templates: Vec<TemplateRenderer>,
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry { templates: crate::config::get().renderers.clone() }
+    }
+
+    /*
+     * Renderers from the configuration file take precedence over the
+     * built-in renderers.
+     */
+    pub fn render(&self, be: &BunyanEntry) -> Option<Rendered> {
+        self.templates
+            .iter()
+            .find_map(|t| t.render(be))
+            .or_else(|| dropshot(be))
+            .or_else(|| steno(be))
+    }
+
+    /*
+     * Replace the message of a record with its rendered form, if any
+     * renderer applies, and remove the fields that the renderer consumed.
+     */
+    pub fn apply(&self, be: &mut BunyanEntry) {
+        if let Some(r) = self.render(be) {
+            be.msg = r.line;
+            for f in r.used {
+                be.extra.remove(&f);
+            }
+        }
+    }
+}
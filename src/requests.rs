@@ -0,0 +1,227 @@
+/*
+ * Follow each request through the log by its ID (the "req_id" field, or
+ * another given with --key), from the "incoming request" record to the
+ * "request completed" record that dropshot logs, and summarise it when it
+ * completes: when it started, how long it took, its status, and how many
+ * other records were logged for it along the way.
+ *
+ * The summary of each request is printed as soon as it completes, so that
+ * we need only remember the requests that are still in progress.  Even so,
+ * a request whose completion is never logged would be remembered forever,
+ * so beyond a limit we forget the requests that we have been waiting on the
+ * longest.
+ */
+
+use std::collections::{HashMap, VecDeque};
+
+use chrono::prelude::*;
+
+use crate::{bold, elapsed, renderers::latency, BunyanEntry, Colour};
+
+const START: &str = "incoming request";
+const DONE: &str = "request completed";
+
+/*
+ * The number of requests in progress at once beyond which we give up on the
+ * oldest.
+ */
+const MAX_OPEN: usize = 100_000;
+
+struct Request {
+    /*
+     * Tells this request apart from an earlier one with the same ID.
+     */
+    seq: u64,
+    /*
+     * The time of the first record for this request, and whether that was
+     * the start of the request.
+     */
+    first: DateTime<Utc>,
+    started: bool,
+    method: Option<String>,
+    uri: Option<String>,
+    records: u64,
+}
+
+pub struct Requests {
+    key: String,
+    colour: Colour,
+    open: HashMap<String, Request>,
+    /*
+     * Open requests, oldest first.  A request may remain here after it has
+     * completed, in which case it is skipped.
+     */
+    order: VecDeque<(u64, String)>,
+    seq: u64,
+    completed: u64,
+    evicted: u64,
+}
+
+fn text(v: &serde_json::Value) -> String {
+    match v {
+        serde_json::Value::String(s) => s.to_string(),
+        other => other.to_string(),
+    }
+}
+
+impl Requests {
+    pub fn new(key: String, colour: Colour) -> Requests {
+        Requests {
+            key,
+            colour,
+            open: HashMap::new(),
+            order: VecDeque::new(),
+            seq: 0,
+            completed: 0,
+            evicted: 0,
+        }
+    }
+
+    pub fn record(&mut self, be: &BunyanEntry) {
+        let Some(id) = be.extra.get(&self.key).map(text) else {
+            return;
+        };
+        let get = |k: &str| be.extra.get(k).map(text);
+
+        if !self.open.contains_key(&id) {
+            self.evict();
+            self.seq += 1;
+            self.order.push_back((self.seq, id.clone()));
+            self.open.insert(
+                id.clone(),
+                Request {
+                    seq: self.seq,
+                    first: be.time,
+                    started: be.msg == START,
+                    method: None,
+                    uri: None,
+                    records: 0,
+                },
+            );
+        }
+        let r = self.open.get_mut(&id).unwrap();
+        r.method = r.method.take().or_else(|| get("method"));
+        r.uri = r.uri.take().or_else(|| get("uri"));
+
+        if be.msg != DONE {
+            if be.msg != START {
+                r.records += 1;
+            }
+            return;
+        }
+
+        /*
+         * Dropshot logs the latency of the request when it completes, which
+         * is more accurate than the time between the records, and is
+         * available even if we did not see the start.
+         */
+        let r = self.open.remove(&id).unwrap();
+        let duration = match be.extra.get("latency_us").and_then(latency) {
+            Some(l) => l,
+            None if r.started => {
+                elapsed(be.time - r.first).trim_start_matches('+').to_string()
+            }
+            None => "-".to_string(),
+        };
+        if self.completed == 0 {
+            println!(
+                "{}",
+                bold(
+                    &format!(
+                        "{:24} {:>12} {:>6} {:>6}  {:36}  REQUEST",
+                        "START", "DURATION", "STATUS", "LOGS", "ID"
+                    ),
+                    self.colour
+                )
+            );
+        }
+        self.completed += 1;
+        println!(
+            "{:24} {:>12} {:>6} {:>6}  {:36}  {} {}",
+            if r.started {
+                r.first.to_rfc3339_opts(SecondsFormat::Millis, true)
+            } else {
+                "-".to_string()
+            },
+            duration,
+            get("response_code").unwrap_or_else(|| "-".to_string()),
+            r.records,
+            id,
+            r.method.as_deref().unwrap_or("-"),
+            r.uri.as_deref().unwrap_or("-"),
+        );
+    }
+
+    fn is_open(&self, seq: u64, id: &str) -> bool {
+        self.open.get(id).is_some_and(|r| r.seq == seq)
+    }
+
+    /*
+     * Make room for another request.
+     */
+    fn evict(&mut self) {
+        while self.open.len() >= MAX_OPEN {
+            let Some((seq, id)) = self.order.pop_front() else {
+                break;
+            };
+            if self.is_open(seq, &id) {
+                self.open.remove(&id);
+                self.evicted += 1;
+            }
+        }
+
+        /*
+         * Don't let completed requests accumulate either.
+         */
+        if self.order.len() > 2 * MAX_OPEN {
+            let open = &self.open;
+            self.order.retain(|(seq, id)| {
+                open.get(id).is_some_and(|r| r.seq == *seq)
+            });
+        }
+    }
+
+    /*
+     * At the end of the input, list the requests that never completed.
+     */
+    pub fn report(&self) {
+        if self.completed == 0 {
+            println!("no completed requests");
+        }
+
+        let open = self
+            .order
+            .iter()
+            .filter(|(seq, id)| self.is_open(*seq, id))
+            .map(|(_, id)| (id, &self.open[id]))
+            .collect::<Vec<_>>();
+        if !open.is_empty() {
+            println!();
+            println!(
+                "{}",
+                bold(
+                    &format!("{} request(s) never completed:", open.len()),
+                    self.colour
+                )
+            );
+            for (id, r) in open {
+                println!(
+                    "    {} {} {} {} ({} records)",
+                    r.first.to_rfc3339_opts(SecondsFormat::Millis, true),
+                    id,
+                    r.method.as_deref().unwrap_or("-"),
+                    r.uri.as_deref().unwrap_or("-"),
+                    r.records,
+                );
+            }
+        }
+
+        if self.evicted > 0 {
+            println!(
+                "note: {} request(s) were forgotten while still in progress, \
+                as more than {} were in progress at once",
+                self.evicted, MAX_OPEN
+            );
+        }
+    }
+}
@@ -0,0 +1,156 @@
+/*
+ * Reconstruct the execution of steno sagas from the events that the saga
+ * executor records for each node: when each node started and finished (or
+ * failed, or was undone), and how the saga as a whole fared.
+ */
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::prelude::*;
+
+use crate::{bold, elapsed, BunyanEntry, Colour};
+
+#[derive(Default)]
+struct Node {
+    started: Option<DateTime<Utc>>,
+    finished: Option<DateTime<Utc>>,
+    outcome: Option<String>,
+}
+
+struct Saga {
+    name: Option<String>,
+    first: DateTime<Utc>,
+    last: DateTime<Utc>,
+    nodes: BTreeMap<u64, Node>,
+}
+
+#[derive(Default)]
+pub struct Sagas {
+    sagas: HashMap<String, Saga>,
+}
+
+/*
+ * The event type is logged in Debug format (e.g., "Succeeded(...)") in the
+ * "event_type" field, or else appears at the end of the message.  We only
+ * need the kind of event.
+ */
+fn event_type(be: &BunyanEntry) -> Option<String> {
+    let ev = match be.extra.get("event_type").and_then(|e| e.as_str()) {
+        Some(e) => e,
+        None => be.msg.rsplit_once(": ")?.1,
+    };
+    let kind = ev
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .next()?
+        .replace('_', "")
+        .to_ascii_lowercase();
+    (!kind.is_empty()).then_some(kind)
+}
+
+impl Sagas {
+    pub fn new() -> Sagas {
+        Default::default()
+    }
+
+    pub fn record(&mut self, be: &BunyanEntry) {
+        let Some(id) = be.extra.get("saga_id").and_then(|i| i.as_str()) else {
+            return;
+        };
+
+        let saga = self.sagas.entry(id.to_string()).or_insert_with(|| Saga {
+            name: None,
+            first: be.time,
+            last: be.time,
+            nodes: Default::default(),
+        });
+        saga.first = saga.first.min(be.time);
+        saga.last = saga.last.max(be.time);
+        if let Some(n) = be.extra.get("saga_name").and_then(|n| n.as_str()) {
+            saga.name = Some(n.to_string());
+        }
+
+        let Some(node) = be.extra.get("node_id").and_then(|n| {
+            n.as_u64().or_else(|| n.as_str().and_then(|s| s.parse().ok()))
+        }) else {
+            return;
+        };
+        let Some(ev) = event_type(be) else {
+            return;
+        };
+
+        let n = saga.nodes.entry(node).or_default();
+        match ev.as_str() {
+            "started" => n.started = Some(be.time),
+            "undostarted" => n.outcome = Some("undoing".into()),
+            "succeeded" | "failed" | "undofinished" | "undofailed" => {
+                n.finished = Some(be.time);
+                n.outcome = Some(match ev.as_str() {
+                    "undofinished" => "undone".into(),
+                    "undofailed" => "undo failed".into(),
+                    other => other.into(),
+                });
+            }
+            _ => (),
+        }
+    }
+
+    pub fn report(&self, colour: Colour) {
+        let mut sagas = self.sagas.iter().collect::<Vec<_>>();
+        sagas.sort_by_key(|(id, s)| (s.first, *id));
+
+        for (id, s) in sagas {
+            let outcomes = s
+                .nodes
+                .values()
+                .filter_map(|n| n.outcome.as_deref())
+                .collect::<Vec<_>>();
+            let status = if outcomes.iter().any(|o| o.contains("fail")) {
+                "failed"
+            } else if outcomes.iter().any(|o| o.starts_with("undo")) {
+                "unwound"
+            } else if s.nodes.values().any(|n| n.finished.is_none()) {
+                "incomplete"
+            } else {
+                "succeeded"
+            };
+
+            println!(
+                "{} {}: {} nodes over {}, {}",
+                bold(
+                    &format!("saga {}", s.name.as_deref().unwrap_or("?")),
+                    colour
+                ),
+                id,
+                s.nodes.len(),
+                elapsed(s.last - s.first).trim_start_matches('+'),
+                status
+            );
+
+            /*
+             * List the nodes in the order in which they started.
+             */
+            let mut nodes = s.nodes.iter().collect::<Vec<_>>();
+            nodes.sort_by_key(|(id, n)| (n.started.unwrap_or(s.first), **id));
+            for (id, n) in nodes {
+                let start = n
+                    .started
+                    .map(|t| elapsed(t - s.first))
+                    .unwrap_or_else(|| "-".into());
+                let took = match (n.started, n.finished) {
+                    (Some(a), Some(b)) => {
+                        elapsed(b - a).trim_start_matches('+').to_string()
+                    }
+                    _ => "-".into(),
+                };
+                println!(
+                    "    node {:>3} {:>10} {:>10}  {}",
+                    id,
+                    start,
+                    took,
+                    n.outcome.as_deref().unwrap_or("running")
+                );
+            }
+            println!();
+        }
+    }
+}
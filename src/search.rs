@@ -0,0 +1,49 @@
+/*
+ * grep-style selection of records by a regular expression that matches the
+ * message (and, if requested, the value of any other field), which covers
+ * the most common filtering without the need to write a rhai script.
+ */
+
+use anyhow::{anyhow, Result};
+
+use crate::{regex::Regex, BunyanEntry};
+
+pub struct Search {
+    patterns: Vec<Regex>,
+    /*
+     * Whether to look at fields other than the message.
+     */
+    fields: bool,
+    invert: bool,
+}
+
+impl Search {
+    pub fn new(
+        patterns: &[String],
+        fields: bool,
+        invert: bool,
+    ) -> Result<Search> {
+        let patterns = patterns
+            .iter()
+            .map(|p| {
+                Regex::new(p).map_err(|e| anyhow!("pattern {:?}: {}", p, e))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Search { patterns, fields, invert })
+    }
+
+    fn hit(&self, s: &str) -> bool {
+        self.patterns.iter().any(|p| p.is_match(s))
+    }
+
+    pub fn matches(&self, be: &BunyanEntry) -> bool {
+        let found = self.hit(&be.msg)
+            || (self.fields
+                && (be.component.as_deref().is_some_and(|c| self.hit(c))
+                    || be.extra.values().any(|v| match v {
+                        serde_json::Value::String(s) => self.hit(s),
+                        v => self.hit(&v.to_string()),
+                    })));
+        found != self.invert
+    }
+}
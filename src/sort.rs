@@ -0,0 +1,200 @@
+/*
+ * Sort an entire input by record timestamp.  Inputs that do not fit in our
+ * memory budget are sorted in chunks which are spilled to temporary files and
+ * then merged.
+ *
+ * Lines that are not records keep their position relative to the record that
+ * precedes them, so that (for example) a panic message stays with the record
+ * logged just before it.
+ */
+
+use std::{
+    cmp::Reverse,
+    collections::hash_map::RandomState,
+    collections::BinaryHeap,
+    fs::{File, OpenOptions},
+    hash::BuildHasher,
+    io::{BufRead, BufReader, BufWriter, Lines, Seek, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::PathBuf,
+};
+
+use anyhow::{anyhow, Result};
+use chrono::{TimeZone, Utc};
+
+use crate::{input::InputLine, parse_line, Line};
+
+/*
+ * The amount of line data we will hold in memory before spilling a sorted
+ * run to disk.
+ */
+const BUFFER_BYTES: usize = 256 * 1024 * 1024;
+
+/*
+ * Sort key: the timestamp (seconds and nanoseconds) followed by the input
+ * sequence number, which keeps the sort stable.
+ */
+type Key = (i64, u32, u64);
+
+/*
+ * Create a temporary file that is ours alone.  The directory may be shared
+ * with other users, so the name is not predictable, and we refuse to open
+ * anything that is already there (such as a symbolic link planted in the
+ * hope that we will write through it).
+ */
+fn create_temp(seq: usize) -> Result<(PathBuf, File)> {
+    let mut tries = 0;
+    loop {
+        let random = RandomState::new().hash_one((std::process::id(), seq));
+        let path = std::env::temp_dir().join(format!(
+            "looker-sort-{}-{:016x}",
+            std::process::id(),
+            random
+        ));
+        match OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+        {
+            Ok(f) => return Ok((path, f)),
+            Err(e)
+                if e.kind() == std::io::ErrorKind::AlreadyExists
+                    && tries < 10 =>
+            {
+                tries += 1;
+            }
+            Err(e) => {
+                return Err(anyhow!("creating temporary file {path:?}: {e}"))
+            }
+        }
+    }
+}
+
+fn spill(run: &mut Vec<(Key, InputLine)>, seq: usize) -> Result<File> {
+    run.sort_unstable_by_key(|r| r.0);
+
+    /*
+     * We keep the file open, and read the run back through the same
+     * descriptor, so it can be removed at once: nothing is left behind
+     * however we exit.
+     */
+    let (path, mut f) = create_temp(seq)?;
+    std::fs::remove_file(&path).ok();
+    crate::diag::emit(
+        crate::diag::Level::Info,
+        "sort-spill",
+        &format!("spilling {} sorted lines to {:?}", run.len(), path),
+        &[("lines", run.len().into())],
+    );
+    let mut w = BufWriter::new(&mut f);
+    for ((s, n, q), l) in run.drain(..) {
+        writeln!(
+            w,
+            "{s} {n} {q} {} {} {} {} {}",
+            l.source,
+            l.number,
+            l.offset,
+            l.arrived.timestamp_nanos_opt().unwrap_or_default(),
+            l.text
+        )?;
+    }
+    w.flush()?;
+    drop(w);
+
+    f.rewind()?;
+    Ok(f)
+}
+
+fn parse_spilled(l: &str) -> Result<(Key, InputLine)> {
+    let mut parts = l.splitn(8, ' ');
+    let mut next = || parts.next().ok_or_else(|| anyhow!("corrupt sort run"));
+    let s = next()?.parse()?;
+    let n = next()?.parse()?;
+    let q = next()?.parse()?;
+    let source = next()?.parse()?;
+    let number = next()?.parse()?;
+    let offset = next()?.parse()?;
+    let arrived = Utc.timestamp_nanos(next()?.parse()?);
+    let text = next()?.to_string();
+    Ok(((s, n, q), InputLine { source, number, offset, arrived, text }))
+}
+
+struct Merge {
+    runs: Vec<Lines<BufReader<File>>>,
+    /*
+     * The next line from each run, ordered in the heap by its key.
+     */
+    heap: BinaryHeap<Reverse<(Key, usize)>>,
+    heads: Vec<Option<InputLine>>,
+}
+
+impl Merge {
+    fn refill(&mut self, i: usize) -> Result<()> {
+        if let Some(l) = self.runs[i].next().transpose()? {
+            let (k, l) = parse_spilled(&l)?;
+            self.heap.push(Reverse((k, i)));
+            self.heads[i] = Some(l);
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for Merge {
+    type Item = Result<InputLine>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((_, i)) = self.heap.pop()?;
+        let l = self.heads[i].take()?;
+        if let Err(e) = self.refill(i) {
+            return Some(Err(e));
+        }
+        Some(Ok(l))
+    }
+}
+
+pub fn sorted(
+    lines: impl Iterator<Item = Result<InputLine>>,
+) -> Result<Box<dyn Iterator<Item = Result<InputLine>>>> {
+    let mut run = Vec::new();
+    let mut bytes = 0;
+    let mut files = Vec::new();
+    let mut last = (i64::MIN, 0);
+
+    for (seq, l) in lines.enumerate() {
+        let l = l?;
+        if let Line::Record(_, be) = parse_line(&l.text) {
+            last = (be.time.timestamp(), be.time.timestamp_subsec_nanos());
+        }
+
+        bytes += l.text.len();
+        run.push(((last.0, last.1, seq as u64), l));
+        if bytes >= BUFFER_BYTES {
+            files.push(spill(&mut run, files.len())?);
+            bytes = 0;
+        }
+    }
+
+    if files.is_empty() {
+        /*
+         * Everything fit in memory, so there is nothing to merge.
+         */
+        run.sort_unstable_by_key(|r| r.0);
+        return Ok(Box::new(run.into_iter().map(|(_, l)| Ok(l))));
+    }
+
+    if !run.is_empty() {
+        files.push(spill(&mut run, files.len())?);
+    }
+
+    let mut m = Merge {
+        heads: files.iter().map(|_| None).collect(),
+        runs: files.into_iter().map(|f| BufReader::new(f).lines()).collect(),
+        heap: BinaryHeap::new(),
+    };
+    for i in 0..m.runs.len() {
+        m.refill(i)?;
+    }
+    Ok(Box::new(m))
+}
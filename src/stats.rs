@@ -0,0 +1,97 @@
+/*
+ * Statistics about the matching records as a whole: how many there were at
+ * each level and from each source (the name, and the component if there is
+ * one), and the span of time they cover, along with the lines that were not
+ * records at all.  This is a quick way to get the measure of an unfamiliar
+ * log before reading it.
+ */
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+};
+
+use chrono::prelude::*;
+
+use crate::{bold, elapsed, level, BunyanEntry, BunyanLevel, Colour};
+
+#[derive(Default)]
+pub struct Stats {
+    total: u64,
+    levels: BTreeMap<Reverse<u8>, (BunyanLevel, u64)>,
+    sources: HashMap<String, u64>,
+    span: Option<(DateTime<Utc>, DateTime<Utc>)>,
+}
+
+impl Stats {
+    pub fn new() -> Stats {
+        Default::default()
+    }
+
+    pub fn record(&mut self, be: &BunyanEntry) {
+        self.total += 1;
+        self.levels
+            .entry(Reverse(be.level.value()))
+            .or_insert((be.level, 0))
+            .1 += 1;
+
+        let source = match &be.component {
+            Some(c) if c != &be.name => format!("{} ({})", be.name, c),
+            _ => be.name.to_string(),
+        };
+        *self.sources.entry(source).or_default() += 1;
+
+        self.span = Some(match self.span {
+            Some((first, last)) => (first.min(be.time), last.max(be.time)),
+            None => (be.time, be.time),
+        });
+    }
+
+    pub fn report(&self, skipped: &BTreeMap<&str, u64>, colour: Colour) {
+        let pct = |n: u64| 100.0 * n as f64 / self.total as f64;
+        let heading = |what: &str| {
+            println!(
+                "{}",
+                bold(&format!("{:>8} {:>6}  {}", "COUNT", "%", what), colour)
+            );
+        };
+
+        match self.span {
+            Some((first, last)) => println!(
+                "{} records from {} to {} ({})",
+                self.total,
+                first.to_rfc3339_opts(SecondsFormat::Millis, true),
+                last.to_rfc3339_opts(SecondsFormat::Millis, true),
+                elapsed(last - first).trim_start_matches('+'),
+            ),
+            None => println!("no records"),
+        }
+
+        if self.total > 0 {
+            println!();
+            heading("LEVEL");
+            for (l, n) in self.levels.values() {
+                println!("{:>8} {:>6.2}  {}", n, pct(*n), level(l, colour));
+            }
+
+            let mut sources = self.sources.iter().collect::<Vec<_>>();
+            sources.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+            println!();
+            heading("SOURCE");
+            for (s, n) in sources {
+                println!("{:>8} {:>6.2}  {}", n, pct(*n), s);
+            }
+        }
+
+        if !skipped.is_empty() {
+            println!();
+            println!(
+                "{}",
+                bold(&format!("{:>8}  NOT A RECORD", "LINES"), colour)
+            );
+            for (why, n) in skipped {
+                println!("{:>8}  {}", n, why);
+            }
+        }
+    }
+}
@@ -0,0 +1,145 @@
+/*
+ * Counts of what was read and what was shown, printed at the end of the
+ * output, so that it is clear how much of the input a filter let through.
+ */
+
+use std::collections::BTreeMap;
+
+use chrono::prelude::*;
+
+use crate::{bold, diag, elapsed, Colour};
+
+#[derive(Default)]
+pub struct Summary {
+    lines: u64,
+    records: u64,
+    unparsed: u64,
+    /*
+     * The number of lines (other than blank ones) that were not records,
+     * by the reason that they were not.
+     */
+    skipped: BTreeMap<&'static str, u64>,
+    displayed: u64,
+    /*
+     * The span of time covered by all of the records, and by those that
+     * were displayed.
+     */
+    span: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    displayed_span: Option<(DateTime<Utc>, DateTime<Utc>)>,
+}
+
+fn widen(
+    span: &mut Option<(DateTime<Utc>, DateTime<Utc>)>,
+    time: DateTime<Utc>,
+) {
+    *span = Some(match *span {
+        Some((first, last)) => (first.min(time), last.max(time)),
+        None => (time, time),
+    });
+}
+
+fn describe(first: DateTime<Utc>, last: DateTime<Utc>) -> String {
+    format!(
+        "from {} to {} ({})",
+        first.to_rfc3339_opts(SecondsFormat::Millis, true),
+        last.to_rfc3339_opts(SecondsFormat::Millis, true),
+        elapsed(last - first).trim_start_matches('+'),
+    )
+}
+
+impl Summary {
+    pub fn new() -> Summary {
+        Default::default()
+    }
+
+    pub fn line(&mut self) {
+        self.lines += 1;
+    }
+
+    pub fn unparsed(&mut self) {
+        self.unparsed += 1;
+    }
+
+    pub fn skipped(&mut self, why: &'static str) {
+        *self.skipped.entry(why).or_default() += 1;
+    }
+
+    pub fn skipped_lines(&self) -> &BTreeMap<&'static str, u64> {
+        &self.skipped
+    }
+
+    pub fn record(&mut self, time: DateTime<Utc>) {
+        self.records += 1;
+        widen(&mut self.span, time);
+    }
+
+    pub fn displayed(&mut self, time: DateTime<Utc>) {
+        self.displayed += 1;
+        widen(&mut self.displayed_span, time);
+    }
+
+    pub fn print(&self, colour: Colour) {
+        println!(
+            "{} {} lines, {} records, {} matched, {} filtered out, {} not \
+            records",
+            bold("---", colour),
+            self.lines,
+            self.records,
+            self.displayed,
+            self.records - self.displayed,
+            self.unparsed,
+        );
+        if let Some((first, last)) = self.span {
+            println!(
+                "{} records {}",
+                bold("---", colour),
+                describe(first, last)
+            );
+        }
+        /*
+         * If some records were filtered out, the span of those that remain
+         * may be rather different.
+         */
+        if self.displayed < self.records {
+            if let Some((first, last)) = self.displayed_span {
+                println!(
+                    "{} matching records {}",
+                    bold("---", colour),
+                    describe(first, last)
+                );
+            }
+        }
+    }
+
+    /*
+     * A note on stderr of the lines that were not records, if there were
+     * any.
+     */
+    pub fn report_skipped(&self) {
+        let n = self.skipped.values().sum::<u64>();
+        if n == 0 {
+            return;
+        }
+        let reasons = self
+            .skipped
+            .iter()
+            .map(|(why, n)| format!("{} {}", n, why))
+            .collect::<Vec<_>>()
+            .join(", ");
+        diag::emit(
+            diag::Level::Warn,
+            "skipped",
+            &format!(
+                "{} {} ({})",
+                n,
+                if n == 1 {
+                    "line was not a record"
+                } else {
+                    "lines were not records"
+                },
+                reasons
+            ),
+            &[("count", n.into())],
+        );
+    }
+}
@@ -0,0 +1,210 @@
+/*
+ * Lines written by syslog, which are read when a line is not JSON.  Both
+ * RFC 5424:
+ *
+ *     <165>1 2024-03-01T10:00:00.123Z gimlet sled-agent 812 - - started
+ *
+ * and the older BSD layout (RFC 3164), as found in /var/log on many
+ * systems, with or without the priority:
+ *
+ *     <30>Mar  1 10:00:00 gimlet sshd[812]: Connection closed
+ *
+ * are turned into the equivalent bunyan record, with the program as the
+ * name.
+ */
+
+use chrono::prelude::*;
+use serde_json::{json, Value};
+
+use crate::timestamp;
+
+const FACILITIES: &[&str] = &[
+    "kern",
+    "user",
+    "mail",
+    "daemon",
+    "auth",
+    "syslog",
+    "lpr",
+    "news",
+    "uucp",
+    "cron",
+    "authpriv",
+    "ftp",
+    "ntp",
+    "security",
+    "console",
+    "solaris-cron",
+    "local0",
+    "local1",
+    "local2",
+    "local3",
+    "local4",
+    "local5",
+    "local6",
+    "local7",
+];
+
+/*
+ * The level for a syslog severity, from 0 (emergency) to 7 (debug).
+ */
+pub fn level(severity: u8) -> Option<u8> {
+    Some(match severity {
+        0..=2 => 60,
+        3 => 50,
+        4 => 40,
+        5 | 6 => 30,
+        7 => 20,
+        _ => return None,
+    })
+}
+
+/*
+ * The priority at the start of a line, as in "<165>", and the rest of it.
+ */
+fn priority(l: &str) -> Option<(Option<u8>, &str)> {
+    let Some(rest) = l.strip_prefix('<') else {
+        return Some((None, l));
+    };
+    let (pri, rest) = rest.split_once('>')?;
+    if pri.is_empty() || pri.len() > 3 {
+        return None;
+    }
+    let pri = pri.parse::<u8>().ok().filter(|p| *p < 192)?;
+    Some((Some(pri), rest))
+}
+
+/*
+ * The structured data of an RFC 5424 line (which is "-" if there is none),
+ * and the message after it.
+ */
+fn structured_data(s: &str) -> Option<(Option<&str>, &str)> {
+    if let Some(rest) = s.strip_prefix('-') {
+        return Some((None, rest.strip_prefix(' ').unwrap_or(rest)));
+    }
+    if !s.starts_with('[') {
+        return None;
+    }
+
+    let (mut quoted, mut escaped) = (false, false);
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ']' if !quoted && !s[i + 1..].starts_with('[') => {
+                let rest = &s[i + 1..];
+                return Some((
+                    Some(&s[..=i]),
+                    rest.strip_prefix(' ').unwrap_or(rest),
+                ));
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+fn nil(s: &str) -> Option<&str> {
+    (s != "-").then_some(s)
+}
+
+fn rfc5424(l: &str) -> Option<Value> {
+    let mut parts = l.strip_prefix("1 ")?.splitn(6, ' ');
+    let time = DateTime::parse_from_rfc3339(parts.next()?).ok()?;
+    let host = nil(parts.next()?);
+    let app = nil(parts.next()?);
+    let procid = nil(parts.next()?);
+    let msgid = nil(parts.next()?);
+    let (sd, msg) = structured_data(parts.next().unwrap_or("-"))?;
+
+    let mut r = record(time.with_timezone(&Utc), host, app, procid, msg);
+    let r_o = r.as_object_mut().unwrap();
+    if let Some(m) = msgid {
+        r_o.insert("msgid".into(), m.into());
+    }
+    if let Some(sd) = sd {
+        r_o.insert("structured_data".into(), sd.into());
+    }
+    Some(r)
+}
+
+/*
+ * The time of a BSD line, which is either in the traditional form (as in
+ * "Mar  1 10:00:00") or, as rsyslog can be configured to write, RFC 3339.
+ */
+fn bsd_time(l: &str) -> Option<(DateTime<Utc>, &str)> {
+    if let Some((t, rest)) = l.split_once(' ') {
+        if let Ok(t) = DateTime::parse_from_rfc3339(t) {
+            return Some((t.with_timezone(&Utc), rest));
+        }
+    }
+
+    /*
+     * A day before the 10th is padded with a space, as in "Mar  1".
+     */
+    let (month, rest) = l.split_once(' ')?;
+    let (day, rest) = rest.trim_start_matches(' ').split_once(' ')?;
+    let (time, rest) = rest.split_once(' ')?;
+
+    let t = timestamp::without_year(
+        &format!("{} {} {}", month, day, time),
+        "%b %d %H:%M:%S%.f",
+    )?;
+    Some((t, rest))
+}
+
+fn bsd(l: &str) -> Option<Value> {
+    let (time, rest) = bsd_time(l)?;
+    let (host, rest) = rest.split_once(' ')?;
+
+    /*
+     * The tag is the name of the program, perhaps with its process ID, as
+     * in "sshd[812]:".
+     */
+    let (app, procid, msg) = match rest.split_once(": ") {
+        Some((tag, msg)) if !tag.contains(' ') => {
+            match tag.strip_suffix(']').and_then(|t| t.split_once('[')) {
+                Some((app, pid)) => (Some(app), Some(pid), msg),
+                None => (Some(tag), None, msg),
+            }
+        }
+        _ => (None, None, rest),
+    };
+    Some(record(time, Some(host), app, procid, msg))
+}
+
+fn record(
+    time: DateTime<Utc>,
+    host: Option<&str>,
+    app: Option<&str>,
+    procid: Option<&str>,
+    msg: &str,
+) -> Value {
+    let pid = procid.and_then(|p| p.parse::<u64>().ok());
+    let mut r = json!({
+        "v": 0,
+        "level": 30,
+        "name": app.unwrap_or("-"),
+        "hostname": host.unwrap_or(""),
+        "pid": pid.unwrap_or(0),
+        "time": time.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        "msg": msg.strip_prefix('\u{feff}').unwrap_or(msg),
+    });
+    if let (Some(p), None) = (procid, pid) {
+        r.as_object_mut().unwrap().insert("procid".into(), p.into());
+    }
+    r
+}
+
+pub fn to_bunyan(l: &str) -> Option<Value> {
+    let (pri, rest) = priority(l)?;
+    let mut r = rfc5424(rest).or_else(|| bsd(rest))?;
+
+    if let Some(pri) = pri {
+        let r_o = r.as_object_mut().unwrap();
+        r_o.insert("level".into(), level(pri & 7)?.into());
+        r_o.insert("facility".into(), FACILITIES[usize::from(pri >> 3)].into());
+    }
+    Some(r)
+}
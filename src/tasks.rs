@@ -0,0 +1,167 @@
+/*
+ * Summarise the activation cycles of Omicron background tasks.  Each cycle
+ * begins with an "activating" record and ends with an "activation complete"
+ * record, both of which identify the task with the "background_task" field
+ * and (usually) the cycle with the "iteration" field.  Cycles are paired up
+ * per task, and we report how long each took along with any that never
+ * completed.
+ */
+
+use std::collections::BTreeMap;
+
+use chrono::prelude::*;
+
+use crate::{bold, elapsed, BunyanEntry, Colour};
+
+const START: &str = "activating";
+const DONE: &str = "activation complete";
+const TASK: &str = "background_task";
+const ITERATION: &str = "iteration";
+
+struct Cycle {
+    task: String,
+    iteration: Option<String>,
+    start: DateTime<Utc>,
+    duration: Option<chrono::Duration>,
+}
+
+#[derive(Default)]
+pub struct Tasks {
+    cycles: Vec<Cycle>,
+    /*
+     * The index in "cycles" of each cycle that has started but not yet
+     * completed, keyed by task and iteration.
+     */
+    open: BTreeMap<(String, Option<String>), usize>,
+    /*
+     * Completions for which we did not see the start of the cycle; e.g.,
+     * because the input begins part way through.
+     */
+    orphans: BTreeMap<String, u64>,
+}
+
+impl Tasks {
+    pub fn new() -> Tasks {
+        Default::default()
+    }
+
+    pub fn record(&mut self, be: &BunyanEntry) {
+        let Some(task) = be.extra.get(TASK).and_then(|t| t.as_str()) else {
+            return;
+        };
+        let iteration = be.extra.get(ITERATION).map(|i| match i {
+            serde_json::Value::String(s) => s.to_string(),
+            other => other.to_string(),
+        });
+        let key = (task.to_string(), iteration.clone());
+
+        if be.msg == START {
+            /*
+             * A task whose cycles are not numbered cannot have more than one
+             * open at a time, so a second start means the first one never
+             * finished.  Leaving it in place as incomplete is sufficient.
+             */
+            self.open.insert(key, self.cycles.len());
+            self.cycles.push(Cycle {
+                task: task.to_string(),
+                iteration,
+                start: be.time,
+                duration: None,
+            });
+        } else if be.msg == DONE {
+            match self.open.remove(&key) {
+                Some(i) => {
+                    let c = &mut self.cycles[i];
+                    c.duration = Some(be.time - c.start);
+                }
+                None => *self.orphans.entry(task.to_string()).or_default() += 1,
+            }
+        }
+    }
+
+    pub fn report(&self, colour: Colour) {
+        let dur = |d: Option<chrono::Duration>| {
+            d.map(|d| elapsed(d).trim_start_matches('+').to_string())
+                .unwrap_or_else(|| "-".to_string())
+        };
+        let iter = |i: &Option<String>| {
+            i.as_ref().map(|i| format!(" #{}", i)).unwrap_or_default()
+        };
+
+        println!(
+            "{}",
+            bold(&format!("{:24} {:>12}  TASK", "START", "DURATION"), colour)
+        );
+        for c in self.cycles.iter() {
+            println!(
+                "{:24} {:>12}  {}{}",
+                c.start.to_rfc3339_opts(SecondsFormat::Millis, true),
+                dur(c.duration),
+                c.task,
+                iter(&c.iteration),
+            );
+        }
+
+        let mut tasks: BTreeMap<&str, (Vec<chrono::Duration>, u64)> =
+            BTreeMap::new();
+        for c in self.cycles.iter() {
+            let t = tasks.entry(&c.task).or_default();
+            match c.duration {
+                Some(d) => t.0.push(d),
+                None => t.1 += 1,
+            }
+        }
+
+        println!();
+        println!(
+            "{}",
+            bold(
+                &format!(
+                    "{:>6} {:>6} {:>12} {:>12} {:>12}  TASK",
+                    "DONE", "OPEN", "MIN", "MEDIAN", "MAX"
+                ),
+                colour
+            )
+        );
+        for (task, (durations, open)) in tasks.iter_mut() {
+            durations.sort();
+            println!(
+                "{:>6} {:>6} {:>12} {:>12} {:>12}  {}",
+                durations.len(),
+                open,
+                dur(durations.first().copied()),
+                dur(durations.get(durations.len() / 2).copied()),
+                dur(durations.last().copied()),
+                task
+            );
+        }
+
+        let incomplete =
+            self.cycles.iter().filter(|c| c.duration.is_none()).count();
+        if incomplete > 0 {
+            println!();
+            println!(
+                "{}",
+                bold(
+                    &format!("{} cycle(s) never completed:", incomplete),
+                    colour
+                )
+            );
+            for c in self.cycles.iter().filter(|c| c.duration.is_none()) {
+                println!(
+                    "    {} {}{}",
+                    c.start.to_rfc3339_opts(SecondsFormat::Millis, true),
+                    c.task,
+                    iter(&c.iteration),
+                );
+            }
+        }
+
+        for (task, n) in self.orphans.iter() {
+            println!(
+                "note: {} completion(s) of {} without a matching start",
+                n, task
+            );
+        }
+    }
+}
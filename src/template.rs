@@ -0,0 +1,112 @@
+/*
+ * Log messages are usually produced from a fixed format string with a few
+ * variable parts interpolated: identifiers, counts, addresses, paths.  To
+ * compare or group messages we reduce each one to an approximation of its
+ * template by replacing those variable parts with placeholders.
+ */
+
+fn is_uuid(s: &str) -> bool {
+    let b = s.as_bytes();
+    b.len() == 36
+        && b.iter().enumerate().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => *c == b'-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+fn is_hexish(s: &str) -> bool {
+    /*
+     * Long runs of hexadecimal digits (hashes, addresses, IPv6 addresses) are
+     * abstracted as a unit, provided they contain at least one digit so that
+     * we do not mistake words like "deadbeef" or "faced" for identifiers.
+     */
+    s.len() >= 8
+        && s.chars().any(|c| c.is_ascii_digit())
+        && s.chars().all(|c| c.is_ascii_hexdigit() || c == ':' || c == '-')
+}
+
+fn abstract_word(w: &str, out: &mut String) {
+    /*
+     * Keep surrounding punctuation (quotes, brackets, trailing commas and
+     * colons) so that the shape of the message is preserved.
+     */
+    let trim: &[char] = &['"', '\'', '(', ')', '[', ']', '{', '}', ',', ';'];
+    let core = w.trim_start_matches(trim);
+    let lead = &w[..w.len() - core.len()];
+    let core = core.trim_end_matches(trim).trim_end_matches(':');
+    let tail = &w[lead.len() + core.len()..];
+
+    out.push_str(lead);
+    if is_uuid(core) {
+        out.push_str("<uuid>");
+    } else if core.starts_with('/') && core.len() > 1 {
+        out.push_str("<path>");
+    } else if is_hexish(core) {
+        out.push_str("<hex>");
+    } else {
+        /*
+         * Replace each run of digits (including decimal points between
+         * digits) with a placeholder; e.g., "sled-12" becomes "sled-<n>" and
+         * "1.5s" becomes "<n>s".
+         */
+        let mut chars = core.chars().peekable();
+        while let Some(c) = chars.next() {
+            if !c.is_ascii_digit() {
+                out.push(c);
+                continue;
+            }
+            while let Some(&n) = chars.peek() {
+                if n.is_ascii_digit() || n == '.' {
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            out.push_str("<n>");
+        }
+    }
+    out.push_str(tail);
+}
+
+pub fn template(msg: &str) -> String {
+    let mut out = String::with_capacity(msg.len());
+    let mut word = String::new();
+    for c in msg.chars() {
+        if c.is_whitespace() {
+            if !word.is_empty() {
+                abstract_word(&word, &mut out);
+                word.clear();
+            }
+            out.push(c);
+        } else {
+            word.push(c);
+        }
+    }
+    if !word.is_empty() {
+        abstract_word(&word, &mut out);
+    }
+    out
+}
+
+/*
+ * The pattern of a request URI, with the query string removed and the path
+ * components that look like identifiers replaced with placeholders; e.g.,
+ * "/v1/instances/<uuid>/disks".
+ */
+pub fn uri_pattern(uri: &str) -> String {
+    let path = uri.split(['?', '#']).next().unwrap_or_default();
+    path.split('/')
+        .map(|c| {
+            if is_uuid(c) {
+                "<uuid>"
+            } else if !c.is_empty() && c.chars().all(|c| c.is_ascii_digit()) {
+                "<n>"
+            } else if is_hexish(c) {
+                "<hex>"
+            } else {
+                c
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
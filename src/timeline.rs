@@ -0,0 +1,102 @@
+/*
+ * Group records by the value of a key field, such as "req_id", and show each
+ * group as a timeline of its own.  This makes it easy to follow a single API
+ * request from one end to the other, even when it is interleaved with many
+ * others.
+ */
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::prelude::*;
+
+use crate::{bold, elapsed, level, BunyanEntry, BunyanLevel, Colour};
+
+struct Step {
+    time: DateTime<Utc>,
+    level: BunyanLevel,
+    component: String,
+    msg: String,
+}
+
+pub struct Timeline {
+    key: String,
+    groups: HashMap<String, Vec<Step>>,
+    /*
+     * Records that do not have the key field are not part of any group.
+     */
+    skipped: u64,
+}
+
+impl Timeline {
+    pub fn new(key: String) -> Timeline {
+        Timeline { key, groups: Default::default(), skipped: 0 }
+    }
+
+    pub fn record(&mut self, be: &BunyanEntry) {
+        let Some(v) = be.extra.get(&self.key) else {
+            self.skipped += 1;
+            return;
+        };
+        let v = match v {
+            serde_json::Value::String(s) => s.to_string(),
+            other => other.to_string(),
+        };
+
+        self.groups.entry(v).or_default().push(Step {
+            time: be.time,
+            level: be.level,
+            component: be.component.as_deref().unwrap_or(&be.name).to_string(),
+            msg: be.msg.lines().next().unwrap_or("").to_string(),
+        });
+    }
+
+    pub fn report(&mut self, colour: Colour) {
+        for steps in self.groups.values_mut() {
+            steps.sort_by_key(|s| s.time);
+        }
+        let mut groups = self.groups.iter().collect::<Vec<_>>();
+        groups.sort_by_key(|(v, steps)| (steps[0].time, *v));
+
+        for (v, steps) in groups {
+            let first = steps[0].time;
+            let last = steps[steps.len() - 1].time;
+
+            let mut components: BTreeMap<&str, u64> = BTreeMap::new();
+            for s in steps.iter() {
+                *components.entry(&s.component).or_default() += 1;
+            }
+            let components = components
+                .iter()
+                .map(|(c, n)| format!("{} ({})", c, n))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            println!(
+                "{}: {} records over {}, from {} to {}",
+                bold(&format!("{} = {}", self.key, v), colour),
+                steps.len(),
+                elapsed(last - first).trim_start_matches('+'),
+                first.format("%H:%M:%S%.3fZ"),
+                last.format("%H:%M:%S%.3fZ"),
+            );
+            println!("    {}", components);
+            for s in steps.iter() {
+                println!(
+                    "    {:>10} {} {}: {}",
+                    elapsed(s.time - first),
+                    level(&s.level, colour),
+                    s.component,
+                    s.msg
+                );
+            }
+            println!();
+        }
+
+        if self.skipped > 0 {
+            println!(
+                "{} records without {} were not shown",
+                self.skipped, self.key
+            );
+        }
+    }
+}
@@ -0,0 +1,126 @@
+/*
+ * Not every producer of bunyan-like records writes the time as an RFC 3339
+ * string.  pino, for one, writes the number of milliseconds since the epoch,
+ * and others use seconds or nanoseconds, so numbers are accepted too, in
+ * whichever unit makes sense for their magnitude.  Strings in a few other
+ * common layouts are accepted as well; a time without an offset is taken to
+ * be in UTC.
+ */
+
+use chrono::prelude::*;
+use serde::{de::Error, Deserialize, Deserializer};
+use serde_json::Value;
+
+/*
+ * The number of nanoseconds in the unit of a time since the epoch, which
+ * may be seconds, milliseconds, microseconds, or nanoseconds.  Counting in
+ * seconds, 1e11 is more than three thousand years from now, and counting in
+ * milliseconds, it is 1973, so the boundaries between the units are not
+ * hard to draw.
+ */
+fn unit(n: f64) -> i128 {
+    match n.abs() {
+        n if n < 1e11 => 1_000_000_000,
+        n if n < 1e14 => 1_000_000,
+        n if n < 1e17 => 1_000,
+        _ => 1,
+    }
+}
+
+fn from_nanos(nanos: i128) -> Option<DateTime<Utc>> {
+    let secs = i64::try_from(nanos.div_euclid(1_000_000_000)).ok()?;
+    DateTime::from_timestamp(secs, nanos.rem_euclid(1_000_000_000) as u32)
+}
+
+pub fn from_epoch(n: f64) -> Option<DateTime<Utc>> {
+    if !n.is_finite() {
+        return None;
+    }
+    /*
+     * The whole and fractional parts are scaled separately, so that a time
+     * such as 1704067201.25 is not a nanosecond out.
+     */
+    let u = unit(n);
+    from_nanos(n.trunc() as i128 * u + (n.fract() * u as f64).round() as i128)
+}
+
+const WITH_OFFSET: &[&str] = &[
+    "%Y-%m-%dT%H:%M:%S%.f%z",
+    "%Y-%m-%d %H:%M:%S%.f%z",
+    "%Y-%m-%d %H:%M:%S%.f %z",
+];
+
+const WITHOUT_OFFSET: &[&str] =
+    &["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f", "%Y/%m/%d %H:%M:%S%.f"];
+
+fn parse_str(s: &str) -> Option<DateTime<Utc>> {
+    let s = s.trim();
+    if let Ok(t) = DateTime::parse_from_rfc3339(s) {
+        return Some(t.with_timezone(&Utc));
+    }
+    if let Ok(n) = s.parse::<f64>() {
+        return from_epoch(n);
+    }
+
+    let naive = s
+        .strip_suffix(" UTC")
+        .or_else(|| s.strip_suffix(['Z', 'z']))
+        .unwrap_or(s);
+    WITH_OFFSET
+        .iter()
+        .find_map(|f| DateTime::parse_from_str(s, f).ok())
+        .or_else(|| DateTime::parse_from_rfc2822(s).ok())
+        .map(|t| t.with_timezone(&Utc))
+        .or_else(|| {
+            WITHOUT_OFFSET
+                .iter()
+                .find_map(|f| NaiveDateTime::parse_from_str(naive, f).ok())
+                .map(|t| t.and_utc())
+        })
+}
+
+/*
+ * A time that is written without a year or zone, as syslog and glog write
+ * them.  We take it to be in the local time zone, in the last year,
+ * counting back from now, in which that time fell.
+ */
+pub fn without_year(s: &str, fmt: &str) -> Option<DateTime<Utc>> {
+    let now = Local::now();
+    let at = |year: i32| {
+        NaiveDateTime::parse_from_str(
+            &format!("{} {}", year, s),
+            &format!("%Y {}", fmt),
+        )
+        .ok()?
+        .and_local_timezone(Local)
+        .earliest()
+    };
+    let t = match at(now.year())? {
+        t if t > now + chrono::Duration::days(1) => at(now.year() - 1)?,
+        t => t,
+    };
+    Some(t.with_timezone(&Utc))
+}
+
+pub fn parse(v: &Value) -> Option<DateTime<Utc>> {
+    match v {
+        Value::String(s) => parse_str(s),
+        /*
+         * Integers are kept as such, as a double cannot represent the
+         * current time in nanoseconds exactly.
+         */
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => from_nanos(i as i128 * unit(i as f64)),
+            None => from_epoch(n.as_f64()?),
+        },
+        _ => None,
+    }
+}
+
+pub fn deserialize<'de, D>(d: D) -> Result<DateTime<Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let v = Value::deserialize(d)?;
+    parse(&v).ok_or_else(|| D::Error::custom(format!("invalid time {}", v)))
+}
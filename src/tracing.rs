@@ -0,0 +1,213 @@
+/*
+ * Records written by the JSON formatter of tracing-subscriber, which Rust
+ * programs that use tracing rather than slog produce.  Rather than handle
+ * them separately throughout, we turn each into the equivalent bunyan
+ * record: the target provides the name (its first component) and the
+ * component (the rest), the fields other than the message become fields of
+ * the record, and so do the spans in which the event occurred.
+ */
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::prelude::*;
+use serde_json::{json, Map, Value};
+
+use crate::{bold, elapsed, BunyanEntry, Colour};
+
+fn level(l: &str) -> Option<u8> {
+    Some(match l.to_ascii_uppercase().as_str() {
+        "ERROR" => 50,
+        "WARN" => 40,
+        "INFO" => 30,
+        "DEBUG" => 20,
+        "TRACE" => 10,
+        _ => return None,
+    })
+}
+
+pub fn to_bunyan(j: &Value) -> Option<Value> {
+    let o = j.as_object()?;
+    let time = o.get("timestamp")?.as_str()?;
+    let level = level(o.get("level")?.as_str()?)?;
+
+    /*
+     * With flatten_event(true), the fields of the event (the message among
+     * them) are properties of the record itself, rather than of "fields".
+     */
+    let fields = match o.get("fields") {
+        Some(f) => Some(f.as_object()?),
+        None if o.contains_key("message") => None,
+        None => return None,
+    };
+    let msg = fields
+        .unwrap_or(o)
+        .get("message")
+        .and_then(Value::as_str)
+        .unwrap_or("");
+
+    let target = o.get("target").and_then(Value::as_str).unwrap_or("");
+    let (name, component) = match target.split_once("::") {
+        Some((n, c)) => (n, Some(c)),
+        None => (target, None),
+    };
+
+    let mut r = json!({
+        "v": 0,
+        "level": level,
+        "name": name,
+        "hostname": "",
+        "pid": 0,
+        "time": time,
+        "msg": msg,
+    });
+    let r_o = r.as_object_mut().unwrap();
+    if let Some(c) = component {
+        r_o.insert("component".into(), c.into());
+    }
+
+    /*
+     * The current span is also the last of the list of spans, if there is
+     * one.  A field cannot replace one of the standard properties.
+     */
+    let extra = fields
+        .into_iter()
+        .flatten()
+        .filter(|(k, _)| *k != "message")
+        .chain(o.iter().filter(|(k, _)| match k.as_str() {
+            "timestamp" | "level" | "fields" | "target" | "message" => false,
+            "span" => !o.contains_key("spans"),
+            _ => true,
+        }));
+    for (k, v) in extra {
+        r_o.entry(k.as_str()).or_insert_with(|| v.clone());
+    }
+    Some(r)
+}
+
+/*
+ * The spans in which the event of a tracing record occurred, outermost
+ * first.
+ */
+pub fn spans(extra: &BTreeMap<String, Value>) -> Vec<&Map<String, Value>> {
+    match (extra.get("spans"), extra.get("span")) {
+        (Some(Value::Array(a)), _) => {
+            a.iter().filter_map(Value::as_object).collect()
+        }
+        (None, Some(Value::Object(s))) => vec![s],
+        _ => Vec::new(),
+    }
+}
+
+/*
+ * With span events enabled (tracing-subscriber's with_span_events), an event
+ * with the message "new" is logged when a span is created, and one with the
+ * message "close" when it ends.  We pair these up by the span stack, fields
+ * and all, as there is no span ID to go by, and add the time between them
+ * to the "close" event.
+ */
+struct Closed {
+    start: DateTime<Utc>,
+    took: chrono::Duration,
+    path: String,
+}
+
+#[derive(Default)]
+pub struct SpanTimes {
+    open: HashMap<String, Vec<DateTime<Utc>>>,
+    /*
+     * Spans that have closed, if we are to report on them.
+     */
+    closed: Option<Vec<Closed>>,
+}
+
+impl SpanTimes {
+    pub fn new(report: bool) -> SpanTimes {
+        SpanTimes { open: HashMap::new(), closed: report.then(Vec::new) }
+    }
+
+    /*
+     * Returns whether the record has been changed.
+     */
+    pub fn observe(&mut self, be: &mut BunyanEntry, j: &mut Value) -> bool {
+        let Some(key) = be.extra.get("spans").or_else(|| be.extra.get("span"))
+        else {
+            return false;
+        };
+        let key = key.to_string();
+
+        match be.msg.as_str() {
+            "new" => {
+                self.open.entry(key).or_default().push(be.time);
+                false
+            }
+            "close" => {
+                let Some(start) = self.open.get_mut(&key).and_then(Vec::pop)
+                else {
+                    return false;
+                };
+                if self.open.get(&key).is_some_and(|s| s.is_empty()) {
+                    self.open.remove(&key);
+                }
+
+                let took = be.time - start;
+                let ms = Value::from(
+                    took.num_microseconds().unwrap_or(i64::MAX) as f64 / 1000.0,
+                );
+                if let Some(o) = j.as_object_mut() {
+                    o.insert("duration_ms".into(), ms.clone());
+                }
+                be.extra.insert("duration_ms".into(), ms);
+
+                if let Some(closed) = &mut self.closed {
+                    closed.push(Closed { start, took, path: path(&be.extra) });
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /*
+     * List the slowest spans, and the number that never closed.
+     */
+    pub fn report(&mut self, n: usize, colour: Colour) {
+        let closed = self.closed.get_or_insert_with(Vec::new);
+        if closed.is_empty() {
+            println!("no spans closed");
+        } else {
+            closed.sort_by_key(|c| std::cmp::Reverse(c.took));
+            println!(
+                "{}",
+                bold(
+                    &format!("{:24} {:>12}  SPAN", "START", "DURATION"),
+                    colour
+                )
+            );
+            for c in closed.iter().take(n) {
+                println!(
+                    "{:24} {:>12}  {}",
+                    c.start.to_rfc3339_opts(SecondsFormat::Millis, true),
+                    elapsed(c.took).trim_start_matches('+'),
+                    c.path
+                );
+            }
+        }
+
+        let open = self.open.values().map(Vec::len).sum::<usize>();
+        if open > 0 {
+            println!();
+            println!("note: {} span(s) never closed", open);
+        }
+    }
+}
+
+/*
+ * The names of the spans of a tracing record, as in "nexus > datastore".
+ */
+pub fn path(extra: &BTreeMap<String, Value>) -> String {
+    spans(extra)
+        .iter()
+        .map(|s| s.get("name").and_then(|n| n.as_str()).unwrap_or("?"))
+        .collect::<Vec<_>>()
+        .join(" > ")
+}
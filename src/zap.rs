@@ -0,0 +1,39 @@
+/*
+ * Records written by zap's production encoder, in which the time ("ts") is
+ * a number of seconds since the epoch, the level is a lower-case name, and
+ * the logger's name (if it has one) is "logger".  The "caller" and
+ * "stacktrace" properties become fields of the record.
+ */
+
+use serde_json::Value;
+
+fn level(l: &str) -> Option<u8> {
+    Some(match l {
+        "debug" => 20,
+        "info" => 30,
+        "warn" => 40,
+        "error" | "dpanic" => 50,
+        "panic" | "fatal" => 60,
+        _ => return None,
+    })
+}
+
+pub fn to_bunyan(j: &Value) -> Option<Value> {
+    let o = j.as_object()?;
+    if o.contains_key("v") || !o.get("ts")?.is_number() {
+        return None;
+    }
+    let level = level(o.get("level")?.as_str()?)?;
+
+    let mut r = o.clone();
+    let ts = r.remove("ts")?;
+    r.insert("v".into(), 0.into());
+    r.insert("time".into(), ts);
+    r.insert("level".into(), level.into());
+    let name = r.remove("logger").unwrap_or_else(|| "-".into());
+    r.insert("name".into(), name);
+    r.entry("hostname").or_insert_with(|| "".into());
+    r.entry("pid").or_insert_with(|| 0.into());
+    r.entry("msg").or_insert_with(|| "".into());
+    Some(Value::Object(r))
+}